@@ -2,9 +2,9 @@
 
 @group(2) @binding(0) var<uniform> size: vec2<f32>; // width, height
 @group(2) @binding(1) var tile_map: texture_2d<f32>;
-@group(2) @binding(2) var<uniform> decoder: array<vec4<f32>, 24>;
+@group(2) @binding(2) var<storage, read> decoder: array<vec4<f32>>;
 
-const CHUNK_SIZE: f32 = 600.0;
+const CHUNK_SIZE: f32 = #{CHUNK_SIZE}.0;
 
 @fragment
 fn fragment(mesh: VertexOutput) -> @location(0) vec4<f32> {
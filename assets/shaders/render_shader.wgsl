@@ -2,13 +2,15 @@
 
 @group(2) @binding(0) var<uniform> size: vec2<f32>; // width, height
 @group(2) @binding(1) var tile_map: texture_2d<f32>;
-@group(2) @binding(2) var<uniform> decoder: array<vec4<f32>, 24>;
+@group(2) @binding(2) var<storage, read> decoder: array<vec4<f32>>;
+@group(2) @binding(3) var damage_map: texture_2d<f32>;
 @group(2) @binding(4) var<storage, read> shadow_map: array<i32, u32(SHADOW_RESOLUTION)>;
 @group(2) @binding(5) var<uniform> global_chunk_position: vec2<f32>;
 @group(2) @binding(6) var<uniform> player_global_position: vec2<f32>;
 
-const CHUNK_SIZE: f32 = 600.0;
-const SHADOW_RESOLUTION: f32 = 2048.;
+const CHUNK_SIZE: f32 = #{CHUNK_SIZE}.0;
+const SHADOW_RESOLUTION: f32 = #{SHADOW_RESOLUTION}.0;
+const CRACK_DARKNESS: f32 = 0.55;
 const LIGHT_PROJECTION : mat3x3<f32> = mat3x3<f32>(
     2.0 / (CHUNK_SIZE * 2),        0.0,                          0.0,
     0.0,                         -2.0 / (CHUNK_SIZE*2),         0.0,
@@ -20,6 +22,10 @@ fn fragment(mesh: VertexOutput) -> @location(0) vec4<f32> {
     let local_coord = vec2<i32>(i32(mesh.uv.x * size.x), i32(mesh.uv.y * size.y));
     let tile_map_value = textureLoad(tile_map, local_coord, 0).r * 255.0;
     var color = decoder[i32(tile_map_value)];
+    let damage = textureLoad(damage_map, local_coord, 0).r;
+    if damage > 0.0 {
+        color = crack(color, local_coord, damage);
+    }
     let is_edge = local_coord.x == 0 || local_coord.x == i32(size.x) - 1 || local_coord.y == 0 || local_coord.y == i32(size.y) - 1;
     if is_edge {
         color = vec4<f32>(144/255., 238/255., 144/255., 1.0); // light green for edges
@@ -40,6 +46,15 @@ fn fragment(mesh: VertexOutput) -> @location(0) vec4<f32> {
     );
 }
 
+// cracks spread from a per pixel threshold so they grow in as damage builds up
+fn crack(color: vec4<f32>, local_coord: vec2<i32>, damage: f32) -> vec4<f32> {
+    let threshold = fract(sin(dot(vec2<f32>(local_coord), vec2<f32>(12.9898, 78.233))) * 43758.5453);
+    if threshold > damage {
+        return vec4<f32>(color.rgb * (1.0 - 0.2 * damage), color.a);
+    }
+    return vec4<f32>(color.rgb * (1.0 - CRACK_DARKNESS), color.a);
+}
+
 fn shade(local_x: f32, local_y: f32, global_chunk_x: f32, global_chunk_y: f32) -> f32 {
     let global_x = get_global_x_coordinate(global_chunk_x, local_x);
     let global_y = get_global_y_coordinate(global_chunk_y, local_y);
@@ -11,8 +11,8 @@
 @group(0) @binding(10) var tile_map_bottom_right: texture_storage_2d<r8unorm, read>;
 
 const WORKGROUP_SIZE: u32 = 2;
-const CHUNK_SIZE: f32 = 600.0;
-const SHADOW_RESOLUTION: f32 = 2048.;
+const CHUNK_SIZE: f32 = #{CHUNK_SIZE}.0;
+const SHADOW_RESOLUTION: f32 = #{SHADOW_RESOLUTION}.0;
 const LIGHT_PROJECTION : mat3x3<f32> = mat3x3<f32>(
     2.0 / (CHUNK_SIZE * 2),        0.0,                          0.0,
     0.0,                         -2.0 / (CHUNK_SIZE * 2),         0.0,
@@ -0,0 +1,41 @@
+use bevy_test::constants::{configure_sizes, DEFAULT_CHUNK_SIZE, DEFAULT_SHADOW_RESOLUTION};
+use bevy_test::material_registry::MaterialRegistry;
+use bevy_test::prefab::load_prefabs;
+use bevy_test::preview::{generate_region, render_preview, write_png, PreviewOptions};
+use bevy_test::seed::WorldSeed;
+use bevy_test::terrain_config::load_terrain_config;
+use bevy_test::util::{arg_value, has_arg};
+
+const USAGE: &str = "usage: world_preview [--seed <seed>] [--chunks <min_x>,<min_y>,<max_x>,<max_y>] [--out <file.png>] [--grid] [--legend] [--chunk-size <size>] [--terrain-config <file.ron>] [--materials <file.ron>]";
+
+fn parse_chunk_rect(value: &str) -> Option<((i32, i32), (i32, i32))> {
+    let numbers: Vec<i32> = value.split(',').map(|number| number.trim().parse().ok()).collect::<Option<_>>()?;
+    let [min_x, min_y, max_x, max_y] = numbers[..] else {
+        return None;
+    };
+    Some(((min_x.min(max_x), min_y.min(max_y)), (min_x.max(max_x), min_y.max(max_y))))
+}
+
+fn main() {
+    if has_arg("--help") {
+        println!("{}", USAGE);
+        return;
+    }
+    configure_sizes(
+        arg_value("--chunk-size").map(|value| value.parse().expect("--chunk-size must be a number")).unwrap_or(DEFAULT_CHUNK_SIZE),
+        DEFAULT_SHADOW_RESOLUTION,
+    );
+    let registry = MaterialRegistry::load();
+    load_terrain_config(&registry);
+    load_prefabs(&registry);
+    let world_seed = WorldSeed::from_args();
+    let (min_chunk, max_chunk) = parse_chunk_rect(&arg_value("--chunks").unwrap_or("-4,-3,4,1".to_string()))
+        .unwrap_or_else(|| panic!("--chunks must be four comma separated integers\n{}", USAGE));
+    let out = arg_value("--out").unwrap_or("preview.png".to_string());
+    let options = PreviewOptions { min_chunk, max_chunk, grid: has_arg("--grid"), legend: has_arg("--legend") };
+    println!("Previewing chunks {:?} to {:?} with seed {}", min_chunk, max_chunk, world_seed.seed);
+    let chunks = generate_region(&world_seed, &options);
+    let image = render_preview(&chunks, &options, &registry);
+    write_png(&image, &out).unwrap_or_else(|err| panic!("Could not write {}: {}", out, err));
+    println!("Wrote {}x{} preview to {}", image.width, image.height, out);
+}
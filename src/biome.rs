@@ -0,0 +1,84 @@
+use noise::Perlin;
+
+use crate::color_map::SKY;
+use crate::seed::{coordinate_hash, BIOME_STREAM};
+use crate::terrain_config::{terrain_config, Biome, TerrainConfig};
+
+pub struct ColumnProfile {
+    pub biome: &'static Biome,
+    pub surface: i32,
+    pub rock_line: i32,
+    pub layer_tops: Vec<i32>,
+}
+
+impl ColumnProfile {
+    pub fn material_at(&self, global_y: i32, variant: usize) -> u8 {
+        if global_y > self.surface {
+            return SKY;
+        }
+        let layer = self.layer_tops.iter().rposition(|top| global_y <= *top).unwrap_or(0);
+        let palette = &self.biome.layers[layer].palette;
+        palette[variant % palette.len()]
+    }
+}
+
+fn smoothstep(t: f64) -> f64 {
+    t * t * (3. - 2. * t)
+}
+
+pub fn column_profile(config: &'static TerrainConfig, perlin: &Perlin, seed: u64, global_x: i32) -> ColumnProfile {
+    let biomes = &config.biomes;
+    let biome_noise = config.biome_noise.sample(perlin, global_x as f64);
+    let position = ((biome_noise + config.biome_noise_range) / (2. * config.biome_noise_range)).clamp(0., 1.) * (biomes.len() - 1) as f64;
+    let index = (position.floor() as usize).min(biomes.len() - 2);
+    let blend = smoothstep(((position - index as f64 - 0.5) / config.biome_transition_width + 0.5).clamp(0., 1.));
+    let (from, to) = (&biomes[index], &biomes[index + 1]);
+    let lerp = |a: f64, b: f64| a + (b - a) * blend;
+    let surface_of = |biome: &Biome| biome.layers[0].height(perlin, global_x);
+    let floor_of = |biome: &Biome| biome.layers.last().unwrap().height(perlin, global_x);
+    let surface = lerp(surface_of(from), surface_of(to));
+    let rock_line = surface + lerp(floor_of(from), floor_of(to));
+    let roll = coordinate_hash(seed, global_x, 0, BIOME_STREAM) as f64 / u64::MAX as f64;
+    let biome = if roll < blend { to } else { from };
+    let last = biome.layers.len() - 1;
+    let layer_tops = biome.layers.iter().enumerate().map(|(layer_index, layer)| match layer_index {
+        0 => surface as i32,
+        _ if layer_index == last => rock_line as i32,
+        _ => (surface + layer.height(perlin, global_x)) as i32,
+    }).collect();
+    ColumnProfile {
+        biome,
+        surface: surface as i32,
+        rock_line: rock_line as i32,
+        layer_tops,
+    }
+}
+
+pub fn surfaces_under(perlin: &Perlin, seed: u64, left_x_g: i32, width: usize) -> (i32, i32) {
+    let surfaces = (left_x_g..left_x_g + width as i32).map(|x_g| column_profile(terrain_config(), perlin, seed, x_g).surface);
+    surfaces.fold((i32::MAX, i32::MIN), |(low, high), surface| (low.min(surface), high.max(surface)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use noise::Perlin;
+
+    use crate::{biome::column_profile, terrain_config::terrain_config};
+
+    #[test]
+    fn test_biome_transitions_are_smooth() {
+        let perlin = Perlin::new(11);
+        let mut biomes = HashSet::new();
+        let mut previous = column_profile(terrain_config(), &perlin, 11, -40000);
+        for global_x in -39999..40000 {
+            let profile = column_profile(terrain_config(), &perlin, 11, global_x);
+            assert!((profile.surface - previous.surface).abs() <= 3, "surface jumps at x={}", global_x);
+            assert!((profile.rock_line - previous.rock_line).abs() <= 6, "rock line jumps at x={}", global_x);
+            biomes.insert(profile.biome.name.as_str());
+            previous = profile;
+        }
+        assert_eq!(biomes.len(), 4);
+    }
+}
@@ -0,0 +1,178 @@
+use crate::constants::chunk_size;
+
+const MAX_PALETTE_SIZE: usize = 16;
+
+#[derive(Clone, Debug)]
+pub enum Chunk {
+    Uniform(u8),
+    Palette {
+        palette: Vec<u8>,
+        bits: u8,
+        data: Vec<u8>,
+    },
+    Dense(Vec<u8>),
+}
+
+impl Chunk {
+    pub fn from_dense(grid: Vec<u8>) -> Chunk {
+        let mut palette: Vec<u8> = Vec::new();
+        for pixel in grid.iter() {
+            if !palette.contains(pixel) {
+                if palette.len() == MAX_PALETTE_SIZE {
+                    return Chunk::Dense(grid);
+                }
+                palette.push(*pixel);
+            }
+        }
+        if palette.len() == 1 {
+            return Chunk::Uniform(palette[0]);
+        }
+        let bits = match palette.len() {
+            2 => 1,
+            3..=4 => 2,
+            _ => 4,
+        };
+        let pixels_per_byte = 8 / bits as usize;
+        let mut data = vec![0; grid.len().div_ceil(pixels_per_byte)];
+        for (index, pixel) in grid.iter().enumerate() {
+            let palette_index = palette.iter().position(|entry| entry == pixel).unwrap() as u8;
+            data[index / pixels_per_byte] |= palette_index << ((index % pixels_per_byte) * bits as usize);
+        }
+        Chunk::Palette { palette, bits, data }
+    }
+
+    pub fn get(&self, index: usize) -> u8 {
+        match self {
+            Chunk::Uniform(pixel) => *pixel,
+            Chunk::Palette { palette, bits, data } => {
+                let pixels_per_byte = 8 / *bits as usize;
+                let shift = (index % pixels_per_byte) * *bits as usize;
+                let mask = (1u8 << *bits) - 1;
+                palette[((data[index / pixels_per_byte] >> shift) & mask) as usize]
+            },
+            Chunk::Dense(grid) => grid[index],
+        }
+    }
+
+    pub fn set(&mut self, index: usize, pixel: u8) {
+        if let Chunk::Dense(grid) = self {
+            grid[index] = pixel;
+            return;
+        }
+        if self.get(index) == pixel {
+            return;
+        }
+        let mut grid = self.to_dense();
+        grid[index] = pixel;
+        *self = Chunk::Dense(grid);
+    }
+
+    pub fn to_dense(&self) -> Vec<u8> {
+        match self {
+            Chunk::Dense(grid) => grid.clone(),
+            _ => {
+                let mut grid = vec![0; (chunk_size() * chunk_size()) as usize];
+                self.write_to(&mut grid);
+                grid
+            }
+        }
+    }
+
+    pub fn write_to(&self, grid: &mut [u8]) {
+        match self {
+            Chunk::Uniform(pixel) => grid.fill(*pixel),
+            Chunk::Palette { .. } => {
+                for (index, pixel) in grid.iter_mut().enumerate() {
+                    *pixel = self.get(index);
+                }
+            },
+            Chunk::Dense(dense) => grid.copy_from_slice(dense),
+        }
+    }
+
+    pub fn rect_data(&self, rect: &DirtyRect) -> Vec<u8> {
+        let mut data = Vec::with_capacity((rect.max_x - rect.min_x + 1) * (rect.max_y - rect.min_y + 1));
+        for y in rect.min_y..=rect.max_y {
+            let row_start = y * chunk_size() as usize;
+            match self {
+                Chunk::Dense(dense) => data.extend_from_slice(&dense[row_start + rect.min_x..=row_start + rect.max_x]),
+                _ => data.extend((rect.min_x..=rect.max_x).map(|x| self.get(row_start + x))),
+            }
+        }
+        data
+    }
+
+    pub fn compact(&mut self) {
+        if let Chunk::Dense(grid) = self {
+            *self = Chunk::from_dense(std::mem::take(grid));
+        }
+    }
+
+    pub fn memory_size(&self) -> usize {
+        match self {
+            Chunk::Uniform(_) => 1,
+            Chunk::Palette { palette, data, .. } => palette.len() + data.len(),
+            Chunk::Dense(grid) => grid.len(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DirtyRect {
+    pub min_x: usize,
+    pub min_y: usize,
+    pub max_x: usize,
+    pub max_y: usize,
+}
+
+impl DirtyRect {
+    pub fn new(local_x: usize, local_y: usize) -> DirtyRect {
+        DirtyRect { min_x: local_x, min_y: local_y, max_x: local_x, max_y: local_y }
+    }
+
+    pub fn full() -> DirtyRect {
+        DirtyRect { min_x: 0, min_y: 0, max_x: chunk_size() as usize - 1, max_y: chunk_size() as usize - 1 }
+    }
+
+    pub fn include(&mut self, local_x: usize, local_y: usize) {
+        self.min_x = self.min_x.min(local_x);
+        self.min_y = self.min_y.min(local_y);
+        self.max_x = self.max_x.max(local_x);
+        self.max_y = self.max_y.max(local_y);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{chunk::Chunk, constants::chunk_size};
+
+    #[test]
+    fn test_uniform_chunk() {
+        let chunk = Chunk::from_dense(vec![5; (chunk_size() * chunk_size()) as usize]);
+        assert!(matches!(chunk, Chunk::Uniform(5)));
+        assert_eq!(chunk.get(1234), 5);
+    }
+
+    #[test]
+    fn test_palette_chunk_round_trip() {
+        let grid: Vec<u8> = (0..(chunk_size() * chunk_size()) as usize).map(|i| [0, 1, 2, 3, 22][i % 5]).collect();
+        let chunk = Chunk::from_dense(grid.clone());
+        assert!(matches!(chunk, Chunk::Palette { bits: 4, .. }));
+        assert_eq!(chunk.to_dense(), grid);
+        assert!(chunk.memory_size() < grid.len());
+    }
+
+    #[test]
+    fn test_set_switches_to_dense() {
+        let mut chunk = Chunk::from_dense(vec![0; (chunk_size() * chunk_size()) as usize]);
+        chunk.set(10, 0);
+        assert!(matches!(chunk, Chunk::Uniform(0)));
+        chunk.set(10, 5);
+        assert!(matches!(chunk, Chunk::Dense(_)));
+        assert_eq!(chunk.get(10), 5);
+        assert_eq!(chunk.get(11), 0);
+        chunk.set(10, 0);
+        chunk.compact();
+        assert!(matches!(chunk, Chunk::Uniform(0)));
+    }
+}
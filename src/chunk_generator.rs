@@ -1,53 +1,162 @@
-use bevy::ecs::{event::{Event, EventReader}, system::Query};
+use std::collections::{HashMap, HashSet};
+
+use bevy::ecs::{event::{Event, EventReader}, system::{Query, Res}};
+use bevy::math::Vec3;
+use bevy::prelude::{Component, Transform, With};
+use bevy::tasks::{block_on, futures_lite::future, AsyncComputeTaskPool, Task};
 use noise::{NoiseFn, Perlin};
+use rand::Rng;
 
-use crate::{color_map::{dirt_variant_pmf, grass_variant_pmf, ROCK, SKY}, components::{ChunkMap, PerlinHandle}, constants::{CHUNK_SIZE, DIRT_NOISE_SMOOTHNESS, DIRT_VARIATION, ROCK_NOISE_SMOOTHNESS, ROCK_VARIATION}, util::{get_global_x_coordinate, get_global_y_coordinate}};
+use crate::{biome::column_profile, chunk::Chunk, color_map::SKY, components::{ChunkMap, PerlinHandle, PlayerTag}, constants::{chunk_size, CAVERN_GROWTH, CAVERN_SMOOTHNESS, CAVERN_THRESHOLD, CAVE_FULL_SIZE_DEPTH, CAVE_ROCK_LINE_OVERLAP, CAVE_WORM_SMOOTHNESS, CAVE_WORM_WIDTH, MAX_CHUNK_TASKS_IN_FLIGHT, MAX_CHUNK_TASKS_PER_FRAME}, material_registry::MaterialRegistry, ore::place_ore_veins, prefab::place_prefabs, region::load_chunk_from_region, seed::{chunk_rng, WorldSeed, LAYER_STREAM}, terrain_config::terrain_config, util::{get_chunk_x_g, get_chunk_y_g, get_global_x_coordinate, get_global_y_coordinate}, vegetation::place_vegetation, water::{lakes_near, place_lakes, place_water_pockets}};
 
 #[derive(Event)]
 pub struct NewChunkEvent{
     pub chunk_x_g: i32,
     pub chunk_y_g: i32,
 }
- 
+
+#[derive(Component)]
+pub struct ChunkGenerationQueue {
+    pub pending: HashSet<(i32, i32)>,
+    pub queued: Vec<(i32, i32)>,
+    pub tasks: HashMap<(i32, i32), Task<LoadedChunk>>,
+}
+
+pub struct LoadedChunk {
+    pub grid: Vec<u8>,
+    pub from_region: bool,
+}
+
 pub fn generate_chunk_listener(
     mut events: EventReader<NewChunkEvent>,
     mut chunk_map_query: Query<&mut ChunkMap>,
+    mut generation_queue_query: Query<&mut ChunkGenerationQueue>,
+    player_query: Query<&Transform, With<PlayerTag>>,
     perlin_query: Query<&PerlinHandle>,
+    world_seed: Res<WorldSeed>,
 ) {
-    for event in events.read() {
-        let perlin = perlin_query.get_single().unwrap().handle;
-        let grid = generate_chunk(event.chunk_x_g, event.chunk_y_g, &perlin);
-        let mut chunk_map = chunk_map_query.get_single_mut().unwrap();
-        chunk_map.map.insert((event.chunk_x_g, event.chunk_y_g), grid);
+    let mut chunk_map = chunk_map_query.get_single_mut().unwrap();
+    let mut generation_queue = generation_queue_query.get_single_mut().unwrap();
+    let requested_chunks = chunk_map.take_requested();
+    for chunk_pos in events.read().map(|event| (event.chunk_x_g, event.chunk_y_g)).chain(requested_chunks) {
+        if chunk_map.map.contains_key(&chunk_pos) || generation_queue.pending.contains(&chunk_pos) {
+            continue;
+        }
+        generation_queue.pending.insert(chunk_pos);
+        generation_queue.queued.push(chunk_pos);
+    }
+    if generation_queue.queued.is_empty() {
+        return;
+    }
+    let player_pos = player_query
+        .get_single()
+        .map(|player| player.translation)
+        .unwrap_or(Vec3::ZERO);
+    let (player_chunk_x_g, player_chunk_y_g) = (get_chunk_x_g(player_pos.x as i32), get_chunk_y_g(player_pos.y as i32));
+    generation_queue.queued.sort_by_key(|(chunk_x_g, chunk_y_g)| std::cmp::Reverse((chunk_x_g - player_chunk_x_g).pow(2) + (chunk_y_g - player_chunk_y_g).pow(2)));
+    let perlin = perlin_query.get_single().unwrap().handle;
+    let seed = world_seed.seed;
+    let task_pool = AsyncComputeTaskPool::get();
+    let mut started = 0;
+    while started < MAX_CHUNK_TASKS_PER_FRAME && generation_queue.tasks.len() < MAX_CHUNK_TASKS_IN_FLIGHT {
+        let Some((chunk_x_g, chunk_y_g)) = generation_queue.queued.pop() else {
+            break;
+        };
+        let task = task_pool.spawn(async move {
+            match load_chunk_from_region(chunk_x_g, chunk_y_g) {
+                Some(grid) => LoadedChunk { grid, from_region: true },
+                None => LoadedChunk { grid: generate_chunk(chunk_x_g, chunk_y_g, &perlin, seed), from_region: false },
+            }
+        });
+        generation_queue.tasks.insert((chunk_x_g, chunk_y_g), task);
+        started += 1;
     }
 }
 
-pub fn generate_chunk(chunk_x_g: i32, chunk_y_g: i32, perlin: &Perlin) -> Vec<u8> {
-    println!("Generating chunk at {}, {}", chunk_x_g, chunk_y_g);
-    let mut grid = vec![0; (CHUNK_SIZE * CHUNK_SIZE) as usize];
-    let mut grass_variant_pmf = grass_variant_pmf();
-    let mut dirt_variant_pmf = dirt_variant_pmf();
-    for x in 0..CHUNK_SIZE as usize {
+pub fn collect_generated_chunks(
+    mut chunk_map_query: Query<&mut ChunkMap>,
+    mut generation_queue_query: Query<&mut ChunkGenerationQueue>,
+    registry: Res<MaterialRegistry>,
+) {
+    let mut chunk_map = chunk_map_query.get_single_mut().unwrap();
+    let mut generation_queue = generation_queue_query.get_single_mut().unwrap();
+    let mut finished = Vec::new();
+    for (chunk_pos, task) in generation_queue.tasks.iter_mut() {
+        if let Some(loaded) = block_on(future::poll_once(task)) {
+            finished.push((*chunk_pos, loaded));
+        }
+    }
+    for (chunk_pos, loaded) in finished {
+        generation_queue.tasks.remove(&chunk_pos);
+        generation_queue.pending.remove(&chunk_pos);
+        if chunk_map.map.contains_key(&chunk_pos) {
+            continue;
+        }
+        chunk_map.map.insert(chunk_pos, Chunk::from_dense(loaded.grid));
+        chunk_map.mark_dirty_chunk(chunk_pos);
+        if loaded.from_region {
+            chunk_map.edited.insert(chunk_pos);
+        }
+        for (x_g, y_g) in chunk_map.unsupported_pixels(chunk_pos, &registry) {
+            chunk_map.wake(x_g, y_g);
+        }
+    }
+}
+
+fn is_cave(perlin: &Perlin, global_x: i32, global_y: i32, rock_line: i32) -> bool {
+    let growth = ((rock_line - global_y) as f64 / CAVE_FULL_SIZE_DEPTH).clamp(0., 1.);
+    let worm = perlin.get([global_x as f64 * CAVE_WORM_SMOOTHNESS, global_y as f64 * CAVE_WORM_SMOOTHNESS, 0.5]);
+    if worm.abs() < CAVE_WORM_WIDTH * (0.5 + growth) {
+        return true;
+    }
+    let cavern = perlin.get([global_x as f64 * CAVERN_SMOOTHNESS, global_y as f64 * CAVERN_SMOOTHNESS, 10.5]);
+    cavern > CAVERN_THRESHOLD - CAVERN_GROWTH * growth
+}
+
+pub fn generate_chunk(chunk_x_g: i32, chunk_y_g: i32, perlin: &Perlin, seed: u64) -> Vec<u8> {
+    let mut grid = vec![0; (chunk_size() * chunk_size()) as usize];
+    let mut layer_rng = chunk_rng(seed, chunk_x_g, chunk_y_g, LAYER_STREAM);
+    for x in 0..chunk_size() as usize {
         let global_x = get_global_x_coordinate(chunk_x_g, x);
-        let dirt_perlin =  perlin.get([global_x as f64 * DIRT_NOISE_SMOOTHNESS, 0.0]) * DIRT_VARIATION;
-        let rock_perlin = perlin.get([global_x as f64 * ROCK_NOISE_SMOOTHNESS, 0.0]) * ROCK_VARIATION;
-        let grass_perlin_top = perlin.get([global_x as f64 * DIRT_NOISE_SMOOTHNESS, 0.0]) * 10.;
-        let grass_perlin_bottom = perlin.get([global_x as f64 * 0.1, 0.0]) * 10.;
-        for y in 0..CHUNK_SIZE as usize {
+        let profile = column_profile(terrain_config(), perlin, seed, global_x);
+        for y in 0..chunk_size() as usize {
             let global_y = get_global_y_coordinate(chunk_y_g, y);
-            let index = y * CHUNK_SIZE as usize + x;
-            if global_y > grass_perlin_top as i32 + 10 {
+            let index = y * chunk_size() as usize + x;
+            grid[index] = profile.material_at(global_y, layer_rng.random::<u32>() as usize);
+            if grid[index] != SKY && global_y < profile.rock_line + CAVE_ROCK_LINE_OVERLAP && is_cave(perlin, global_x, global_y, profile.rock_line) {
                 grid[index] = SKY;
-            } else if global_y > grass_perlin_bottom as i32 - 5 {
-                grid[index] = grass_variant_pmf.next().unwrap();
-            } else if global_y > dirt_perlin as i32 {
-                grid[index] = dirt_variant_pmf.next().unwrap();
-            } else if global_y > -100 + rock_perlin as i32 {
-                grid[index] = dirt_variant_pmf.next().unwrap();
-            } else {
-                grid[index] = ROCK;
             }
         }
     }
+    place_ore_veins(&mut grid, chunk_x_g, chunk_y_g, seed);
+    let lakes = lakes_near(seed, chunk_x_g, perlin);
+    place_lakes(&mut grid, chunk_x_g, chunk_y_g, &lakes, perlin, seed);
+    place_water_pockets(&mut grid, chunk_size() as i32, chunk_x_g, chunk_y_g, perlin, seed);
+    place_vegetation(&mut grid, chunk_x_g, chunk_y_g, &lakes, perlin, seed);
+    place_prefabs(&mut grid, chunk_x_g, chunk_y_g, perlin, seed);
     grid
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use noise::Perlin;
+
+    use crate::{chunk_generator::generate_chunk, color_map::SKY, seed::WorldSeed};
+
+    #[test]
+    fn test_generation_is_deterministic() {
+        let world_seed = WorldSeed::from_seed_string("deterministic");
+        let perlin = Perlin::new(world_seed.perlin_seed());
+        assert_eq!(generate_chunk(2, 0, &perlin, world_seed.seed), generate_chunk(2, 0, &perlin, world_seed.seed));
+        assert_ne!(generate_chunk(2, 0, &perlin, world_seed.seed), generate_chunk(2, 0, &perlin, world_seed.seed + 1));
+    }
+
+    #[test]
+    fn test_caves_grow_with_depth() {
+        let world_seed = WorldSeed::from_seed_string("caves");
+        let perlin = Perlin::new(world_seed.perlin_seed());
+        let cave_pixels = |chunk_y_g: i32| (-1..=1).map(|chunk_x_g| generate_chunk(chunk_x_g, chunk_y_g, &perlin, world_seed.seed).iter().filter(|pixel| **pixel == SKY).count()).sum::<usize>();
+        assert!(cave_pixels(-1) > 0);
+        assert!(cave_pixels(-1) < cave_pixels(-6));
+    }
+}
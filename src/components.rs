@@ -1,7 +1,11 @@
 use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
 use bevy::{prelude::Component, time::Timer};
 use noise::Perlin;
 
+use crate::chunk::{Chunk, DirtyRect};
+use crate::util::{get_chunk_x_g, get_chunk_y_g, get_local_x, get_local_y};
+
 #[derive(Component, Debug)]
 pub struct Velocity {
     pub vx: f32,
@@ -51,11 +55,6 @@ pub struct ContentList {
     pub contents: Vec<u8>
 }
 
-#[derive(Component, Debug)]
-pub struct GravityCoords {
-    pub coords: HashSet<(i32, i32)>
-}
-
 #[derive(Component)]
 pub struct FogIndicesToUncover {
     pub indices: HashSet<usize>
@@ -98,11 +97,6 @@ pub struct Rock {
     pub vertical_force: usize
 }
 
-#[derive(Component)]
-pub struct ErosionCoords {
-    pub coords: HashSet<(usize, usize)>
-}
-
 #[derive(Component)]
 pub struct USize {
     pub usize: usize
@@ -110,7 +104,36 @@ pub struct USize {
 
 #[derive(Component)]
 pub struct ChunkMap {
-    pub map: HashMap<(i32, i32), Vec<u8>>,
+    pub map: HashMap<(i32, i32), Chunk>,
+    pub edited: HashSet<(i32, i32)>,
+    pub dirty: HashMap<(i32, i32), DirtyRect>,
+    pub damage: HashMap<(i32, i32), HashMap<usize, f32>>,
+    pub active: HashMap<(i32, i32), DirtyRect>,
+    pub requested: Mutex<HashSet<(i32, i32)>>,
+}
+
+impl ChunkMap {
+    pub fn new(map: HashMap<(i32, i32), Chunk>) -> ChunkMap {
+        ChunkMap { map, edited: HashSet::new(), dirty: HashMap::new(), damage: HashMap::new(), active: HashMap::new(), requested: Mutex::new(HashSet::new()) }
+    }
+
+    pub fn mark_dirty(&mut self, x_g: i32, y_g: i32) {
+        let chunk_pos = (get_chunk_x_g(x_g), get_chunk_y_g(y_g));
+        let (local_x, local_y) = (get_local_x(x_g), get_local_y(y_g));
+        self.edited.insert(chunk_pos);
+        self.dirty.entry(chunk_pos)
+            .and_modify(|rect| rect.include(local_x, local_y))
+            .or_insert(DirtyRect::new(local_x, local_y));
+    }
+
+    pub fn mark_dirty_chunk(&mut self, chunk_pos: (i32, i32)) {
+        self.dirty.insert(chunk_pos, DirtyRect::full());
+    }
+}
+
+#[derive(Component)]
+pub struct ChunkLoader {
+    pub radius: i32,
 }
 
 #[derive(Component)]
@@ -13,12 +13,18 @@ use bevy::{
 };
 use bytemuck::{Pod, Zeroable};
 use wgpu::util;
-use crate::{color_map::{apply_gamma_correction, RAW_DECODER_DATA}, components::TerrainImageTag, constants::{CHUNK_SIZE, SHADOW_RESOLUTION}, materials::GridMaterial, util::grid_to_image};
+use crate::{components::TerrainImageTag, constants::{chunk_size, shadow_resolution}, material_registry::MaterialRegistry, materials::{size_shader_defs, GridMaterial}, util::grid_to_image};
 
 const SHADER_ASSET_PATH: &str = "shaders/shadow_compute.wgsl";
-const INPUT_SIZE: (u32, u32) = (CHUNK_SIZE as u32 * 3, CHUNK_SIZE as u32 * 3);
 const WORKGROUP_SIZE: u32 = 2;
-const OUTPUT_BUFFER_SIZE: u64 = SHADOW_RESOLUTION as u64 * std::mem::size_of::<f32>() as u64 * 4;
+
+fn input_size() -> (u32, u32) {
+    (chunk_size() as u32 * 3, chunk_size() as u32 * 3)
+}
+
+fn output_buffer_size() -> u64 {
+    shadow_resolution() as u64 * std::mem::size_of::<f32>() as u64 * 4
+}
 
 #[derive(Resource, Clone, ExtractResource)]
 pub struct ScreenImageHandles {
@@ -67,12 +73,14 @@ pub fn build_compute_shader(
     mut materials: ResMut<Assets<GridMaterial>>,
     mut meshes: ResMut<Assets<Mesh>>,
     render_device: Res<RenderDevice>,
+    registry: Res<MaterialRegistry>,
 ) {
     commands.insert_resource::<CurrentPlayerPosition>(CurrentPlayerPosition::default());
     let mut map = HashMap::new();
+    let decoder = registry.decoder();
     let shadow_buffer = render_device.create_buffer(&BufferDescriptor {
         label: Some("Shadow Buffer"),
-        size: OUTPUT_BUFFER_SIZE * size_of::<f32>() as u64,
+        size: output_buffer_size() * size_of::<f32>() as u64,
         mapped_at_creation: false,
         usage: wgpu::BufferUsages::STORAGE,
     });
@@ -82,26 +90,29 @@ pub fn build_compute_shader(
     for x in [-1, 0, 1] {
         for y in [-1, 0, 1] {
             let mut input_image = grid_to_image(
-                &vec![188; (CHUNK_SIZE * CHUNK_SIZE) as usize],
-                CHUNK_SIZE as u32,
-                CHUNK_SIZE as u32,
+                &vec![188; (chunk_size() * chunk_size()) as usize],
+                chunk_size() as u32,
+                chunk_size() as u32,
                 None,
             );
             input_image.texture_descriptor.usage = TextureUsages::COPY_DST | TextureUsages::STORAGE_BINDING | TextureUsages::TEXTURE_BINDING;
             let input_handle = images.add(input_image);
             map.insert([x, y], input_handle.clone());
+            let damage_handle = images.add(grid_to_image(&vec![0; (chunk_size() * chunk_size()) as usize], chunk_size() as u32, chunk_size() as u32, None));
             commands.spawn(TerrainImageTag)
                     .insert(MaterialMesh2dBundle {
                         material: materials.add(GridMaterial {
                             color_map_handle: input_handle,
-                            size: Vec2::new(CHUNK_SIZE as f32, CHUNK_SIZE as f32),
-                            decoder: apply_gamma_correction(RAW_DECODER_DATA),
+                            size: Vec2::new(chunk_size() as f32, chunk_size() as f32),
+                            decoder: decoder.clone(),
+                            damage_map_handle: damage_handle,
                             global_chunk_pos: Vec2::new(x as f32, y as f32),
                             on_screen_chunk_position: [x, y],
+                            displayed_chunk: None,
                             player_pos: Vec2::new(0., 0.),
                             shadow_map: shadow_buffer.clone(),
                         }),
-                        mesh: meshes.add(Rectangle { half_size: Vec2::new(CHUNK_SIZE/2., CHUNK_SIZE/2.) }).into(),
+                        mesh: meshes.add(Rectangle { half_size: Vec2::new(chunk_size()/2., chunk_size()/2.) }).into(),
                         transform: Transform { translation: Vec3::new(Default::default(), Default::default(), -5.), ..Default::default() },
                         ..Default::default()
                     });
@@ -261,7 +272,7 @@ impl FromWorld for ShadowsPipeline {
                     ty: BindingType::Buffer {
                         ty: BufferBindingType::Storage { read_only: false },
                         has_dynamic_offset: false,
-                        min_binding_size: BufferSize::new(OUTPUT_BUFFER_SIZE),
+                        min_binding_size: BufferSize::new(output_buffer_size()),
                     },
                     count: None,
                 }]);
@@ -276,7 +287,7 @@ impl FromWorld for ShadowsPipeline {
             layout: vec![bind_group_0.clone(), bind_group_1.clone(), bind_group_2.clone()],
             push_constant_ranges: Vec::new(),
             shader: shader.clone(),
-            shader_defs: vec![],
+            shader_defs: size_shader_defs(),
             entry_point: Cow::from("init"),
         });
         let update_pipeline = pipeline_cache.queue_compute_pipeline(ComputePipelineDescriptor {
@@ -284,7 +295,7 @@ impl FromWorld for ShadowsPipeline {
             layout: vec![bind_group_0.clone(), bind_group_1.clone(), bind_group_2.clone()],
             push_constant_ranges: Vec::new(),
             shader,
-            shader_defs: vec![],
+            shader_defs: size_shader_defs(),
             entry_point: Cow::from("update"),
         });
         ShadowsPipeline {
@@ -456,7 +467,7 @@ impl render_graph::Node for ShadowsNode {
             pass.set_bind_group(1, &bind_group_1, &[]);
             pass.set_bind_group(2, &bind_group_2, &[]);
             pass.set_pipeline(init_pipeline);
-            pass.dispatch_workgroups(INPUT_SIZE.0 / WORKGROUP_SIZE, INPUT_SIZE.1 / WORKGROUP_SIZE, 1);
+            pass.dispatch_workgroups(input_size().0 / WORKGROUP_SIZE, input_size().1 / WORKGROUP_SIZE, 1);
         } else {
             return Ok(());
         }
@@ -465,7 +476,7 @@ impl render_graph::Node for ShadowsNode {
             pass.set_bind_group(1, &bind_group_1, &[]);
             pass.set_bind_group(2, &bind_group_2, &[]);
             pass.set_pipeline(update_pipeline);
-            pass.dispatch_workgroups(INPUT_SIZE.0 / WORKGROUP_SIZE, INPUT_SIZE.1 / WORKGROUP_SIZE, 1);
+            pass.dispatch_workgroups(input_size().0 / WORKGROUP_SIZE, input_size().1 / WORKGROUP_SIZE, 1);
         } else {
             return Ok(());
         }
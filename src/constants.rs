@@ -1,3 +1,5 @@
+use std::sync::OnceLock;
+
 //Cheats
 pub const MAX_SHOVEL_CAPACITY: usize = 20000000;
 pub const CURSOR_RADIUS: usize = 15 * 1;
@@ -7,9 +9,25 @@ pub const NO_GRAVITY: bool = false;
 //Setup
 pub const WINDOW_WIDTH: usize = 1200;
 pub const WINDOW_HEIGHT: usize = 1200;
-pub const CHUNK_SIZE: f32 = 600 as f32;
+pub const DEFAULT_CHUNK_SIZE: f32 = 600 as f32;
 pub const LIGHTING_DEMO: bool = false;
-pub const SHADOW_RESOLUTION: f32 = 2048.;
+pub const DEFAULT_SHADOW_RESOLUTION: f32 = 2048.;
+pub const WORLD_SAVE_PATH: &str = "saves/world.sav";
+pub const WORLD_FORMAT_VERSION: u32 = 1;
+pub const REGION_DIRECTORY: &str = "saves/regions";
+pub const REGION_SIZE: i32 = 8;
+pub const CHUNK_LOAD_RADIUS: i32 = 3;
+pub const CHUNK_EVICTION_INTERVAL: f32 = 2.;
+pub const MAX_REGION_WRITES_PER_EVICTION: usize = 2;
+pub const CHUNK_COMPACTION_INTERVAL: f32 = 1.;
+pub const MAX_CHUNK_TASKS_PER_FRAME: usize = 4;
+pub const MAX_CHUNK_TASKS_IN_FLIGHT: usize = 16;
+pub const MAX_EDIT_HISTORY: usize = 64;
+pub const HEADLESS_TICK_RATE: f64 = 60.;
+pub const DEFAULT_SIMULATION_TICK_RATE: f64 = 144.;
+pub const MAX_SIMULATION_CATCH_UP_STEPS: u32 = 4;
+pub const MIN_TIME_SCALE: f64 = 0.125;
+pub const MAX_TIME_SCALE: f64 = 8.;
 
 //Player
 pub const PLAYER_WIDTH: usize = 20;
@@ -22,9 +40,6 @@ pub const MAX_STEP_HEIGHT: i32 = 3;
 //other
 pub const MAX_PLAYER_SPEED: f32 = 80.;
 pub const SHOW_COLLISION_BOX: bool = true;
-pub const GROUND_HEIGHT: usize = (CHUNK_SIZE as f32 * 0.2) as usize;
-pub const ROCK_HEIGHT: usize = (CHUNK_SIZE as f32 * 0.4) as usize;
-pub const SKY_HEIGHT: usize = (CHUNK_SIZE as f32 * 0.4) as usize;
 pub const MIN_CLOUD_HEIGHT: usize = 30;
 pub const MAX_CLOUD_HEIGHT: usize = 80;
 pub const MAX_CLOUD_SPEED: f32 = 20.;
@@ -32,23 +47,58 @@ pub const SUN_SIZE: usize = 100;
 pub const MOON_SIZE: usize = 50;
 
 //terrain gen
-pub const DIRT_NOISE_SMOOTHNESS: f64 = 0.003;
-pub const DIRT_VARIATION: f64 = 15.;
-pub const ROCK_NOISE_SMOOTHNESS: f64 = 0.004;
-pub const ROCK_VARIATION: f64 = 80.;
+pub const TERRAIN_CONFIG_PATH: &str = "assets/terrain.ron";
+pub const MATERIALS_PATH: &str = "assets/materials.ron";
+pub const PREFAB_DIR: &str = "assets/prefabs";
+pub const PREFAB_MAX_SURFACE_RANGE: i32 = 6;
+pub const TREE_SPACING: i32 = 12;
+pub const TREE_MIN_HEIGHT: i32 = 10;
+pub const TREE_MAX_HEIGHT: i32 = 22;
+pub const TREE_CANOPY_RADIUS: i32 = 5;
+pub const BUSH_RADIUS: i32 = 3;
+pub const TALL_GRASS_MAX_HEIGHT: i32 = 3;
+pub const LEAF_FILL: f64 = 0.85;
+pub const CAVE_ROCK_LINE_OVERLAP: i32 = 20;
+pub const CAVE_FULL_SIZE_DEPTH: f64 = 2000.;
+pub const CAVE_WORM_SMOOTHNESS: f64 = 0.008;
+pub const CAVE_WORM_WIDTH: f64 = 0.03;
+pub const CAVERN_SMOOTHNESS: f64 = 0.004;
+pub const CAVERN_THRESHOLD: f64 = 0.6;
+pub const CAVERN_GROWTH: f64 = 0.2;
+pub const LAKE_MIN_WIDTH: i32 = 30;
+pub const LAKE_MAX_WIDTH: i32 = 90;
+pub const LAKE_MIN_DEPTH: i32 = 6;
+pub const LAKE_MAX_DEPTH: i32 = 16;
+pub const LAKE_MAX_SURFACE_RANGE: i32 = 12;
+pub const WATER_POCKET_CHANCE: f32 = 0.3;
+pub const WATER_POCKET_MIN_RADIUS: i32 = 6;
+pub const WATER_POCKET_MAX_RADIUS: i32 = 18;
+pub const WATER_POCKET_MIN_DEPTH: i32 = 80;
 
 
 pub const GRAVITY: f32 = 10.;
 
 pub const MAX_LAYERS: usize = 4;
 pub const CURSOR_BORDER_WIDTH: f32 = 1.5;
-pub const MIN_EROSION_HEIGHT: i32 = 3;
+pub const SIMULATION_WAKE_MARGIN: i32 = 4;
+pub const LIQUID_FLOW_DISTANCE: i32 = 4;
+pub const LIQUID_LEVEL_MAX_BODY: usize = 4096;
+pub const LIQUID_DRAG: f32 = 4.;
+pub const LIQUID_SWIM_ACCELERATION: f32 = 450.;
 pub const FRICTION: f32 = 22.0;
 pub const SELL_BOX_HEIGHT: usize = 16;
 pub const SELL_BOX_WIDTH: usize = 40;
 pub const MAX_COPPER_ORE_SPAWNS: usize = 12;
 pub const COPPER_SPAWN_RADIUS: i32 = 40;
-pub const ROCK_STRENGTH: usize = 500;
+pub const COPPER_MIN_DEPTH: f32 = 50.;
+pub const COPPER_VEIN_FILL: f32 = 0.6;
+pub const MAX_SILVER_ORE_SPAWNS: usize = 6;
+pub const SILVER_SPAWN_RADIUS: i32 = 20;
+pub const SILVER_MIN_DEPTH: f32 = 600.;
+pub const SILVER_VEIN_FILL: f32 = 0.5;
+pub const ORE_FULL_DENSITY_DEPTH: f32 = 3000.;
+pub const PICKAXE_POWER: f32 = 3.;
+pub const PICKAXE_FALLOFF: f32 = 0.75;
 pub const STARTING_FOGLESS: usize = 30;
 pub const SUN_SPAWN_X: usize = 0;
 pub const SUN_SPAWN_Y: usize = 0;
@@ -70,8 +120,38 @@ pub const SHOW_RAYS: bool = true;
 pub const NUM_BOXES_IN_TOOL_BAR: usize = 6;
 pub const TOOL_BAR_BOX_SIZE: usize = 30; 
 pub const RENDER_SIZE: i32 = 3;
-pub const MAX_DIRT_HEIGHT_G: f64 = 0.;
-pub const MAX_ROCK_HEIGHT_G: f64 = -100.; 
+pub const PREVIEW_FONT_SCALE: usize = 2;
+pub const PREVIEW_GRID_OPACITY: f32 = 0.35;
 pub const SELL_BOX_SPAWN_X: i32 = 400;
 pub const SELL_BOX_SPAWN_Y: i32 = 400;
-pub const SPAWN_SELL_BOX: bool = false;
\ No newline at end of file
+pub const SPAWN_SELL_BOX: bool = false;
+pub const MARKET_VOLATILITY: f32 = 0.02;
+pub const MARKET_REVERSION: f32 = 0.01;
+pub const MARKET_MIN_DRIFT: f32 = 0.5;
+pub const MARKET_MAX_DRIFT: f32 = 1.5;
+pub const MARKET_FLOOD_IMPACT: f32 = 0.002;
+pub const MARKET_MAX_DEPRESSION: f32 = 0.9;
+pub const MARKET_RECOVERY_RATE: f32 = 0.05;
+
+static CHUNK_SIZE: OnceLock<f32> = OnceLock::new();
+static SHADOW_RESOLUTION: OnceLock<f32> = OnceLock::new();
+
+pub fn chunk_size() -> f32 {
+    *CHUNK_SIZE.get_or_init(|| DEFAULT_CHUNK_SIZE)
+}
+
+pub fn shadow_resolution() -> f32 {
+    *SHADOW_RESOLUTION.get_or_init(|| DEFAULT_SHADOW_RESOLUTION)
+}
+
+pub fn configure_sizes(chunk_size: f32, shadow_resolution: f32) {
+    if chunk_size < 2. || chunk_size.fract() != 0. || chunk_size % 2. != 0. {
+        panic!("Chunk size must be a positive even integer, got {}", chunk_size);
+    }
+    if shadow_resolution < 1. || shadow_resolution.fract() != 0. {
+        panic!("Shadow resolution must be a positive integer, got {}", shadow_resolution);
+    }
+    if CHUNK_SIZE.set(chunk_size).is_err() || SHADOW_RESOLUTION.set(shadow_resolution).is_err() {
+        panic!("Chunk size and shadow resolution must be configured before they are first read");
+    }
+}
@@ -0,0 +1,245 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::time::Duration;
+
+use bevy::app::{App, AppExit, Last, Plugin, PreUpdate, Update};
+use bevy::ecs::event::EventWriter;
+use bevy::input::ButtonInput;
+use bevy::log::info;
+use bevy::math::Vec2;
+use bevy::prelude::{KeyCode, MouseButton, Query, ResMut, Resource, Transform, With};
+use bevy::time::TimeUpdateStrategy;
+
+use crate::components::{ChunkLoader, ChunkMap, ContentList, Count, PlayerTag};
+use crate::constants::HEADLESS_TICK_RATE;
+use crate::mouse_controller::CursorPosition;
+use crate::tools::ShovelTag;
+use crate::util::{arg_value, get_chunk_x_g, get_chunk_y_g};
+
+pub struct HeadlessPlugin;
+
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ButtonInput<KeyCode>>();
+        app.init_resource::<ButtonInput<MouseButton>>();
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(1. / HEADLESS_TICK_RATE)));
+        app.add_systems(Update, request_chunks_near_loaders);
+        app.add_systems(Last, clear_dirty_rects);
+        if let Some(script_path) = arg_value("--script") {
+            let source = fs::read_to_string(&script_path).unwrap_or_else(|err| panic!("Could not read input script {}: {}", script_path, err));
+            let commands = parse_script(&source).unwrap_or_else(|err| panic!("Could not parse input script {}: {}", script_path, err));
+            app.insert_resource(InputScript { commands: commands.into(), wait_frames: 0, releases: Vec::new(), cursor_offset: None, frame: 0 });
+            app.add_systems(PreUpdate, run_input_script);
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ScriptButton {
+    Key(KeyCode),
+    Mouse(MouseButton),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ScriptCommand {
+    Wait(u32),
+    Press(ScriptButton),
+    Release(ScriptButton),
+    Tap(ScriptButton),
+    Cursor(f32, f32),
+    Print,
+    Exit,
+}
+
+#[derive(Resource)]
+pub struct InputScript {
+    pub commands: VecDeque<ScriptCommand>,
+    pub wait_frames: u32,
+    pub releases: Vec<ScriptButton>,
+    pub cursor_offset: Option<Vec2>,
+    pub frame: u64,
+}
+
+fn parse_button(name: &str) -> Option<ScriptButton> {
+    let key = match name {
+        "MouseLeft" => return Some(ScriptButton::Mouse(MouseButton::Left)),
+        "MouseRight" => return Some(ScriptButton::Mouse(MouseButton::Right)),
+        "A" => KeyCode::KeyA,
+        "D" => KeyCode::KeyD,
+        "Z" => KeyCode::KeyZ,
+        "Space" => KeyCode::Space,
+        "ShiftLeft" => KeyCode::ShiftLeft,
+        "ControlLeft" => KeyCode::ControlLeft,
+        "F5" => KeyCode::F5,
+        "P" => KeyCode::KeyP,
+        "Period" => KeyCode::Period,
+        "BracketLeft" => KeyCode::BracketLeft,
+        "BracketRight" => KeyCode::BracketRight,
+        "1" => KeyCode::Digit1,
+        "2" => KeyCode::Digit2,
+        "3" => KeyCode::Digit3,
+        "4" => KeyCode::Digit4,
+        _ => return None,
+    };
+    Some(ScriptButton::Key(key))
+}
+
+pub fn parse_script(source: &str) -> Result<Vec<ScriptCommand>, String> {
+    let mut commands = Vec::new();
+    for (line_number, line) in source.lines().enumerate() {
+        let line = line.split('#').next().unwrap().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let error = |message: &str| format!("line {}: {} in \"{}\"", line_number + 1, message, line);
+        let button = |index: usize| words.get(index).and_then(|name| parse_button(name)).ok_or_else(|| error("unknown button"));
+        let number = |index: usize| words.get(index).and_then(|value| value.parse::<f32>().ok()).ok_or_else(|| error("expected a number"));
+        let command = match words[0] {
+            "wait" => ScriptCommand::Wait(number(1)? as u32),
+            "press" => ScriptCommand::Press(button(1)?),
+            "release" => ScriptCommand::Release(button(1)?),
+            "tap" => ScriptCommand::Tap(button(1)?),
+            "cursor" => ScriptCommand::Cursor(number(1)?, number(2)?),
+            "print" => ScriptCommand::Print,
+            "exit" => ScriptCommand::Exit,
+            _ => return Err(error("unknown command")),
+        };
+        commands.push(command);
+    }
+    Ok(commands)
+}
+
+fn set_button(button: ScriptButton, pressed: bool, keys: &mut ButtonInput<KeyCode>, buttons: &mut ButtonInput<MouseButton>) {
+    match (button, pressed) {
+        (ScriptButton::Key(key), true) => keys.press(key),
+        (ScriptButton::Key(key), false) => keys.release(key),
+        (ScriptButton::Mouse(mouse_button), true) => buttons.press(mouse_button),
+        (ScriptButton::Mouse(mouse_button), false) => buttons.release(mouse_button),
+    }
+}
+
+pub fn run_input_script(
+    mut script: ResMut<InputScript>,
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    mut buttons: ResMut<ButtonInput<MouseButton>>,
+    mut cursor_position: ResMut<CursorPosition>,
+    player_query: Query<&Transform, With<PlayerTag>>,
+    chunk_map_query: Query<&ChunkMap>,
+    shovel_contents_query: Query<&ContentList, With<ShovelTag>>,
+    money_count_query: Query<&Count>,
+    mut exit: EventWriter<AppExit>,
+) {
+    keys.clear();
+    buttons.clear();
+    script.frame += 1;
+    for button in std::mem::take(&mut script.releases) {
+        set_button(button, false, &mut keys, &mut buttons);
+    }
+    if script.wait_frames > 0 {
+        script.wait_frames -= 1;
+    }
+    while script.wait_frames == 0 {
+        let Some(command) = script.commands.pop_front() else {
+            info!("Input script finished after {} frames", script.frame);
+            exit.send(AppExit::Success);
+            break;
+        };
+        match command {
+            ScriptCommand::Wait(frames) => script.wait_frames = frames,
+            ScriptCommand::Press(button) => set_button(button, true, &mut keys, &mut buttons),
+            ScriptCommand::Release(button) => set_button(button, false, &mut keys, &mut buttons),
+            ScriptCommand::Tap(button) => {
+                set_button(button, true, &mut keys, &mut buttons);
+                script.releases.push(button);
+            },
+            ScriptCommand::Cursor(dx, dy) => script.cursor_offset = Some(Vec2::new(dx, dy)),
+            ScriptCommand::Print => {
+                let player_pos = player_query.get_single().map(|player| player.translation).unwrap_or_default();
+                info!(
+                    "frame {}: player at {:.1}, {:.1}, money {:.2}, shovel holds {}, {} chunks loaded",
+                    script.frame,
+                    player_pos.x,
+                    player_pos.y,
+                    money_count_query.get_single().map(|count| count.count).unwrap_or(0.),
+                    shovel_contents_query.get_single().map(|contents| contents.contents.len()).unwrap_or(0),
+                    chunk_map_query.get_single().map(|chunk_map| chunk_map.map.len()).unwrap_or(0),
+                );
+            },
+            ScriptCommand::Exit => {
+                info!("Input script exited after {} frames", script.frame);
+                exit.send(AppExit::Success);
+                script.commands.clear();
+                break;
+            },
+        }
+    }
+    if let (Some(offset), Ok(player)) = (script.cursor_offset, player_query.get_single()) {
+        cursor_position.position = Some(player.translation.truncate() + offset);
+    }
+}
+
+pub fn request_chunks_near_loaders(
+    loader_query: Query<&Transform, With<ChunkLoader>>,
+    chunk_map_query: Query<&ChunkMap>,
+) {
+    let chunk_map = chunk_map_query.get_single().unwrap();
+    for transform in loader_query.iter() {
+        let (chunk_x_g, chunk_y_g) = (get_chunk_x_g(transform.translation.x as i32), get_chunk_y_g(transform.translation.y as i32));
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                if !chunk_map.map.contains_key(&(chunk_x_g + dx, chunk_y_g + dy)) {
+                    chunk_map.request((chunk_x_g + dx, chunk_y_g + dy));
+                }
+            }
+        }
+    }
+}
+
+// Without a window nothing uploads the dirty rects, so they are dropped every frame
+pub fn clear_dirty_rects(mut chunk_map_query: Query<&mut ChunkMap>) {
+    if let Ok(mut chunk_map) = chunk_map_query.get_single_mut() {
+        chunk_map.dirty.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::app::{App, AppExit, PreUpdate, Update};
+    use bevy::input::ButtonInput;
+    use bevy::prelude::{IntoSystemConfigs, KeyCode, MouseButton};
+
+    use crate::{headless::{parse_script, run_input_script, InputScript, ScriptButton, ScriptCommand}, mouse_controller::CursorPosition, time_control::{update_time_scale, TimeScale}};
+    #[test]
+    fn test_parse_script() {
+        let commands = parse_script("# dig and walk\ncursor 0 -40\ntap MouseLeft\nwait 30 # let gravity settle\npress D\nexit\n").unwrap();
+        assert_eq!(commands, vec![
+            ScriptCommand::Cursor(0., -40.),
+            ScriptCommand::Tap(ScriptButton::Mouse(MouseButton::Left)),
+            ScriptCommand::Wait(30),
+            ScriptCommand::Press(ScriptButton::Key(KeyCode::KeyD)),
+            ScriptCommand::Exit,
+        ]);
+        assert!(parse_script("press Q").is_err());
+    }
+
+    #[test]
+    fn test_script_changes_time_scale() {
+        let commands = parse_script("tap BracketRight\nwait 1\ntap BracketRight\nwait 1\ntap BracketLeft\nwait 1\ntap P\nwait 1\n").unwrap();
+        let mut app = App::new();
+        app.add_event::<AppExit>()
+            .init_resource::<ButtonInput<KeyCode>>()
+            .init_resource::<ButtonInput<MouseButton>>()
+            .init_resource::<CursorPosition>()
+            .init_resource::<TimeScale>()
+            .insert_resource(InputScript { commands: commands.into(), wait_frames: 0, releases: Vec::new(), cursor_offset: None, frame: 0 })
+            .add_systems(PreUpdate, run_input_script)
+            .add_systems(Update, update_time_scale.after(run_input_script));
+        let scales: Vec<f64> = (0..4).map(|_| {
+            app.update();
+            app.world().resource::<TimeScale>().scale
+        }).collect();
+        assert_eq!(scales, vec![2., 4., 2., 2.]);
+        assert!(app.world().resource::<TimeScale>().paused);
+    }
+}
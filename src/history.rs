@@ -0,0 +1,238 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use bevy::input::ButtonInput;
+use bevy::log::warn;
+use bevy::prelude::{Component, KeyCode, Query, Res, With};
+
+use crate::components::{ChunkMap, ContentList, Count};
+use crate::constants::MAX_EDIT_HISTORY;
+use crate::terrain_world::ChunkNotLoaded;
+use crate::tools::ShovelTag;
+
+#[derive(Default, Debug)]
+pub struct EditTransaction {
+    pub pixels: HashMap<(i32, i32), (u8, u8)>,
+    pub moves: Vec<((i32, i32), (i32, i32))>,
+    pub sales: HashMap<(i32, i32), f32>,
+    pub shovel_pushed: Vec<u8>,
+    pub shovel_popped: Vec<u8>,
+    pub money: f32,
+}
+
+impl EditTransaction {
+    pub fn set(&mut self, chunk_map: &mut ChunkMap, x_g: i32, y_g: i32, pixel: u8) -> Result<(), ChunkNotLoaded> {
+        let old = chunk_map.try_get(x_g, y_g)?;
+        chunk_map.set(x_g, y_g, pixel)?;
+        self.record(x_g, y_g, old, pixel);
+        Ok(())
+    }
+
+    pub fn swap(&mut self, chunk_map: &mut ChunkMap, a: (i32, i32), b: (i32, i32)) -> Result<(), ChunkNotLoaded> {
+        let pixel_a = chunk_map.try_get(a.0, a.1)?;
+        let pixel_b = chunk_map.try_get(b.0, b.1)?;
+        chunk_map.swap(a, b)?;
+        self.record(a.0, a.1, pixel_a, pixel_b);
+        self.record(b.0, b.1, pixel_b, pixel_a);
+        self.moves.push((a, b));
+        Ok(())
+    }
+
+    pub fn record(&mut self, x_g: i32, y_g: i32, old: u8, new: u8) {
+        self.pixels.entry((x_g, y_g))
+            .and_modify(|edit| edit.1 = new)
+            .or_insert((old, new));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pixels.is_empty() && self.shovel_pushed.is_empty() && self.shovel_popped.is_empty()
+    }
+
+    // Pixels of a later transaction that touch this one, directly or through other
+    // touching pixels, along with anything they were swapped with
+    fn chained(&self, later: &EditTransaction) -> HashSet<(i32, i32)> {
+        let mut partners: HashMap<(i32, i32), Vec<(i32, i32)>> = HashMap::new();
+        for (a, b) in later.moves.iter() {
+            partners.entry(*a).or_default().push(*b);
+            partners.entry(*b).or_default().push(*a);
+        }
+        let neighbors = |(x_g, y_g): (i32, i32)| (-1..=1).flat_map(move |dx| (-1..=1).map(move |dy| (x_g + dx, y_g + dy)));
+        let mut chained = HashSet::new();
+        let mut frontier: Vec<(i32, i32)> = later.pixels.keys()
+            .filter(|position| neighbors(**position).any(|neighbor| self.pixels.contains_key(&neighbor)))
+            .cloned()
+            .collect();
+        while let Some(position) = frontier.pop() {
+            if !chained.insert(position) {
+                continue;
+            }
+            frontier.extend(neighbors(position).filter(|neighbor| later.pixels.contains_key(neighbor) && !chained.contains(neighbor)));
+            frontier.extend(partners.get(&position).into_iter().flatten().filter(|partner| !chained.contains(*partner)));
+        }
+        chained
+    }
+
+    fn matches(&self, chunk_map: &ChunkMap, shovel_contents: &[u8], expected: fn(&(u8, u8)) -> u8, shovel_tail: &[u8]) -> bool {
+        self.pixels.iter().all(|((x_g, y_g), edit)| chunk_map.get(*x_g, *y_g) == Some(expected(edit)))
+            && shovel_contents.ends_with(shovel_tail)
+    }
+
+    fn apply(&self, chunk_map: &mut ChunkMap, target: fn(&(u8, u8)) -> u8) {
+        for ((x_g, y_g), edit) in self.pixels.iter() {
+            chunk_map.set(*x_g, *y_g, target(edit)).unwrap();
+        }
+    }
+}
+
+#[derive(Component, Default)]
+pub struct EditHistory {
+    pub undo: VecDeque<EditTransaction>,
+    pub redo: Vec<EditTransaction>,
+}
+
+impl EditHistory {
+    pub fn commit(&mut self, transaction: EditTransaction) {
+        if transaction.is_empty() {
+            return;
+        }
+        self.redo.clear();
+        self.undo.push_back(transaction);
+        if self.undo.len() > MAX_EDIT_HISTORY {
+            self.undo.pop_front();
+        }
+    }
+
+    // Folds the knock-on effects of the latest edit, such as pixels it left unsupported,
+    // into it. Motion elsewhere in the world is not part of the edit and is left alone.
+    pub fn absorb(&mut self, transaction: EditTransaction) {
+        let Some(latest) = self.undo.back_mut() else {
+            return;
+        };
+        let chained = latest.chained(&transaction);
+        if chained.is_empty() {
+            return;
+        }
+        for position in chained {
+            let (old, new) = transaction.pixels[&position];
+            latest.record(position.0, position.1, old, new);
+            latest.money += transaction.sales.get(&position).unwrap_or(&0.);
+        }
+        self.redo.clear();
+    }
+
+    pub fn undo(&mut self, chunk_map: &mut ChunkMap, shovel_contents: &mut Vec<u8>, money: &mut f32) -> bool {
+        let Some(transaction) = self.undo.pop_back() else {
+            return false;
+        };
+        if !transaction.matches(chunk_map, shovel_contents, |edit| edit.1, &transaction.shovel_pushed) {
+            warn!("Terrain no longer matches the edit history, clearing {} undo steps", self.undo.len() + 1);
+            self.undo.clear();
+            return false;
+        }
+        transaction.apply(chunk_map, |edit| edit.0);
+        shovel_contents.truncate(shovel_contents.len() - transaction.shovel_pushed.len());
+        shovel_contents.extend(transaction.shovel_popped.iter().rev());
+        *money -= transaction.money;
+        self.redo.push(transaction);
+        true
+    }
+
+    pub fn redo(&mut self, chunk_map: &mut ChunkMap, shovel_contents: &mut Vec<u8>, money: &mut f32) -> bool {
+        let Some(transaction) = self.redo.pop() else {
+            return false;
+        };
+        let popped_tail: Vec<u8> = transaction.shovel_popped.iter().rev().cloned().collect();
+        if !transaction.matches(chunk_map, shovel_contents, |edit| edit.0, &popped_tail) {
+            warn!("Terrain no longer matches the edit history, clearing {} redo steps", self.redo.len() + 1);
+            self.redo.clear();
+            return false;
+        }
+        transaction.apply(chunk_map, |edit| edit.1);
+        shovel_contents.truncate(shovel_contents.len() - popped_tail.len());
+        shovel_contents.extend(transaction.shovel_pushed.iter());
+        *money += transaction.money;
+        self.undo.push_back(transaction);
+        true
+    }
+}
+
+pub fn undo_redo(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut chunk_map_query: Query<&mut ChunkMap>,
+    mut edit_history_query: Query<&mut EditHistory>,
+    mut shovel_contents_query: Query<&mut ContentList, With<ShovelTag>>,
+    mut money_count_query: Query<&mut Count>,
+) {
+    if !keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) || !keys.just_pressed(KeyCode::KeyZ) {
+        return;
+    }
+    let mut chunk_map = chunk_map_query.get_single_mut().unwrap();
+    let mut edit_history = edit_history_query.get_single_mut().unwrap();
+    let mut shovel_contents = shovel_contents_query.get_single_mut().unwrap();
+    let mut money_count = money_count_query.get_single_mut().unwrap();
+    if keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+        edit_history.redo(&mut chunk_map, &mut shovel_contents.contents, &mut money_count.count);
+    } else {
+        edit_history.undo(&mut chunk_map, &mut shovel_contents.contents, &mut money_count.count);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{chunk::Chunk, color_map::{DIRT1, DIRT2, SKY}, components::ChunkMap, history::{EditHistory, EditTransaction}};
+
+    #[test]
+    fn test_undo_follows_gravity() {
+        let mut map = HashMap::new();
+        map.insert((0, 0), Chunk::Uniform(SKY));
+        let mut chunk_map = ChunkMap::new(map);
+        let mut history = EditHistory::default();
+        let mut shovel_contents = vec![DIRT1];
+        let mut money = 0.;
+        let mut transaction = EditTransaction::default();
+        transaction.set(&mut chunk_map, 0, 10, shovel_contents.pop().unwrap()).unwrap();
+        transaction.shovel_popped.push(DIRT1);
+        history.commit(transaction);
+        let mut gravity = EditTransaction::default();
+        for y in (1..=10).rev() {
+            gravity.swap(&mut chunk_map, (0, y), (0, y - 1)).unwrap();
+        }
+        history.absorb(gravity);
+        assert_eq!(chunk_map.get(0, 0), Some(DIRT1));
+        assert!(history.undo(&mut chunk_map, &mut shovel_contents, &mut money));
+        assert_eq!(chunk_map.get(0, 0), Some(SKY));
+        assert_eq!(chunk_map.get(0, 10), Some(SKY));
+        assert_eq!(shovel_contents, vec![DIRT1]);
+        assert!(history.redo(&mut chunk_map, &mut shovel_contents, &mut money));
+        assert_eq!(chunk_map.get(0, 0), Some(DIRT1));
+        assert!(shovel_contents.is_empty());
+    }
+
+    #[test]
+    fn test_undo_ignores_unrelated_motion() {
+        let mut map = HashMap::new();
+        map.insert((0, 0), Chunk::Uniform(SKY));
+        let mut chunk_map = ChunkMap::new(map);
+        chunk_map.set(50, 40, DIRT2).unwrap();
+        let mut history = EditHistory::default();
+        let mut transaction = EditTransaction::default();
+        transaction.set(&mut chunk_map, 0, 10, DIRT1).unwrap();
+        history.commit(transaction);
+        for tick in 0..30 {
+            let mut gravity = EditTransaction::default();
+            if tick < 10 {
+                gravity.swap(&mut chunk_map, (0, 10 - tick), (0, 9 - tick)).unwrap();
+            }
+            gravity.swap(&mut chunk_map, (50, 40 - tick), (50, 39 - tick)).unwrap();
+            history.absorb(gravity);
+        }
+        assert_eq!(chunk_map.get(0, 0), Some(DIRT1));
+        assert_eq!(chunk_map.get(50, 10), Some(DIRT2));
+        assert!(history.undo(&mut chunk_map, &mut Vec::new(), &mut 0.));
+        assert_eq!(chunk_map.get(0, 0), Some(SKY));
+        assert_eq!(chunk_map.get(0, 10), Some(SKY));
+        assert_eq!(chunk_map.get(50, 10), Some(DIRT2));
+        assert_eq!(chunk_map.get(50, 40), Some(SKY));
+    }
+}
@@ -1,35 +1,18 @@
-use bevy::{ecs::event::EventWriter, input::ButtonInput, prelude::{KeyCode, Query, Res, Transform, Visibility, With, Without}, time::Time};
+use bevy::{input::ButtonInput, prelude::{KeyCode, Query, Res, Transform, Visibility, With, Without}, time::Time};
 
-use crate::{chunk_generator::NewChunkEvent, components::{Bool, ChunkMap, ContentList, PlayerTag, Velocity}, constants::{FRICTION, MAX_PLAYER_SPEED, PLAYER_ACCELERATION, PLAYER_HEIGHT, PLAYER_WIDTH}, player::apply_velocity, tools::{CurrentTool, HoeTag, PickaxeTag, ShovelTag, Tool}, world_generation::does_gravity_apply_to_entity};
+use crate::{components::{Bool, ChunkMap, ContentList, PlayerTag, Velocity}, constants::{FRICTION, LIQUID_SWIM_ACCELERATION, MAX_PLAYER_SPEED, PLAYER_ACCELERATION, PLAYER_HEIGHT, PLAYER_WIDTH}, material_registry::MaterialRegistry, player::{apply_velocity, is_submerged}, tools::{CurrentTool, HoeTag, PickaxeTag, ShovelTag, Tool}, world_generation::does_gravity_apply_to_entity};
 
 pub fn process_key_event(
     keys: Res<ButtonInput<KeyCode>>,
-    mut player_query: Query<(&mut Transform, &mut Velocity), With<PlayerTag>>,
-    time: Res<Time>,
     shovel_contents_query: Query<&mut ContentList, With<ShovelTag>>,
     mut current_tool_query: Query<&mut CurrentTool>,
     mut shovel_visability_query: Query<&mut Visibility, (With<ShovelTag>, Without<PickaxeTag>, Without<HoeTag>)>,
     mut pickaxe_visability_query: Query<&mut Visibility, (With<PickaxeTag>, Without<ShovelTag>, Without<HoeTag>)>,
     mut hoe_visability_query: Query<&mut Visibility, (With<HoeTag>, Without<PickaxeTag>, Without<ShovelTag>)>,
     mut hoe_is_locked_query: Query<&mut Bool, With<HoeTag>>,
-    mut chunk_map_query: Query<&mut ChunkMap>,
-    mut chunk_event_writer: EventWriter<NewChunkEvent>,
 ) {
     let shovel_contents = shovel_contents_query.get_single().unwrap();
-    let chunk_map = &mut chunk_map_query.get_single_mut().unwrap();
     let mut hoe_is_locked = hoe_is_locked_query.get_single_mut().unwrap();
-    let mut player = player_query.get_single_mut().unwrap();
-    let does_gravity_apply = does_gravity_apply_to_entity(player.0.translation, PLAYER_WIDTH as i32, PLAYER_HEIGHT as i32, &mut chunk_map.map, &mut chunk_event_writer);
-    if does_gravity_apply{
-        player.1.vy -= 300. * time.delta_seconds();
-    } else {
-        player.1.vy = 0.;
-        if player.1.vx > 0. {
-            player.1.vx = (player.1.vx - FRICTION * time.delta_seconds()).max(0.);
-        } else if player.1.vx < 0. {
-            player.1.vx = (player.1.vx + FRICTION * time.delta_seconds()).min(0.);
-        }
-    }
     if keys.pressed(KeyCode::Digit1) {
         hoe_is_locked.bool = false;
         let mut shovel_visability = shovel_visability_query.get_single_mut().unwrap();
@@ -69,6 +52,28 @@ pub fn process_key_event(
         *hoe_visability = Visibility::Hidden;
         current_tool.tool = Tool::SpawnDrill;
     }
+}
+
+pub fn move_player(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut player_query: Query<(&mut Transform, &mut Velocity), With<PlayerTag>>,
+    time: Res<Time>,
+    chunk_map_query: Query<&ChunkMap>,
+    registry: Res<MaterialRegistry>,
+) {
+    let chunk_map = chunk_map_query.get_single().unwrap();
+    let mut player = player_query.get_single_mut().unwrap();
+    let does_gravity_apply = does_gravity_apply_to_entity(player.0.translation, PLAYER_WIDTH as i32, PLAYER_HEIGHT as i32, &chunk_map, &registry);
+    if does_gravity_apply{
+        player.1.vy -= 300. * time.delta_seconds();
+    } else {
+        player.1.vy = 0.;
+        if player.1.vx > 0. {
+            player.1.vx = (player.1.vx - FRICTION * time.delta_seconds()).max(0.);
+        } else if player.1.vx < 0. {
+            player.1.vx = (player.1.vx + FRICTION * time.delta_seconds()).min(0.);
+        }
+    }
     if keys.pressed(KeyCode::KeyA) {
         player.1.vx = (player.1.vx - PLAYER_ACCELERATION * time.delta_seconds())
             .max(-MAX_PLAYER_SPEED);
@@ -79,6 +84,8 @@ pub fn process_key_event(
     }
     if keys.pressed(KeyCode::Space) && !does_gravity_apply {
         player.1.vy += 150.;
+    } else if keys.pressed(KeyCode::Space) && is_submerged(chunk_map, &player.0.translation, &registry) {
+        player.1.vy += LIQUID_SWIM_ACCELERATION * time.delta_seconds();
     }
-    apply_velocity(&mut player.0.translation, &mut player.1, &chunk_map.map, &time);
+    apply_velocity(&mut player.0.translation, &mut player.1, &chunk_map, &time, &registry);
 }
\ No newline at end of file
@@ -0,0 +1,34 @@
+pub mod world_generation;
+pub mod constants;
+pub mod player;
+pub mod components;
+pub mod util;
+pub mod mouse_controller;
+pub mod keyboard_controller;
+pub mod fog;
+pub mod materials;
+pub mod tools;
+pub mod color_map;
+pub mod ui;
+pub mod drill;
+pub mod render;
+pub mod chunk_generator;
+pub mod compute_shader;
+pub mod save;
+pub mod region;
+pub mod chunk;
+pub mod seed;
+pub mod terrain_world;
+pub mod history;
+pub mod headless;
+pub mod ore;
+pub mod biome;
+pub mod terrain_config;
+pub mod prefab;
+pub mod vegetation;
+pub mod preview;
+pub mod material_registry;
+pub mod market;
+pub mod water;
+pub mod simulation;
+pub mod time_control;
@@ -1,81 +1,129 @@
-mod world_generation;
-pub mod constants;
-pub mod player;
-pub mod components;
-pub mod util;
-pub mod mouse_controller;
-pub mod keyboard_controller;
-pub mod fog;
-pub mod materials;
-pub mod tools;
-pub mod color_map;
-pub mod ui;
-pub mod drill;
-pub mod render;
-pub mod chunk_generator;
-pub mod compute_shader;
+use std::time::Duration;
 
 use bevy::app::*;
 use bevy::diagnostic::EntityCountDiagnosticsPlugin;
 use bevy::diagnostic::FrameTimeDiagnosticsPlugin;
 use bevy::diagnostic::SystemInformationDiagnosticsPlugin;
 use bevy::ecs::schedule::ScheduleBuildSettings;
+use bevy::log::LogPlugin;
 use bevy::prelude::*;
 use bevy::sprite::Material2dPlugin;
+use bevy::time::common_conditions::on_timer;
 use bevy::window::PresentMode;
-use chunk_generator::generate_chunk_listener;
-use compute_shader::build_compute_shader;
-use compute_shader::ShadowsComputePlugin;
-use constants::LIGHTING_DEMO;
-use constants::WINDOW_HEIGHT;
+use bevy_test::chunk_generator::collect_generated_chunks;
+use bevy_test::chunk_generator::generate_chunk_listener;
+use bevy_test::chunk_generator::NewChunkEvent;
+use bevy_test::compute_shader::build_compute_shader;
+use bevy_test::compute_shader::ShadowsComputePlugin;
+use bevy_test::constants::CHUNK_COMPACTION_INTERVAL;
+use bevy_test::constants::CHUNK_EVICTION_INTERVAL;
+use bevy_test::constants::configure_sizes;
+use bevy_test::constants::DEFAULT_CHUNK_SIZE;
+use bevy_test::constants::DEFAULT_SHADOW_RESOLUTION;
+use bevy_test::constants::LIGHTING_DEMO;
+use bevy_test::constants::WINDOW_HEIGHT;
 use iyes_perf_ui::PerfUiPlugin;
-use keyboard_controller::process_key_event;
-use mouse_controller::check_mouse_click;
-use player::spawn_player;
-use materials::DefaultMaterial;
-use materials::GridMaterial;
-use tools::spawn_tools;
-use tools::update_tool;
-use world_generation::setup_camera;
-use world_generation::setup_world;
-use world_generation::grid_tick;
-use crate::constants::WINDOW_WIDTH;
-use crate::render::render;
+use bevy_test::headless::HeadlessPlugin;
+use bevy_test::history::undo_redo;
+use bevy_test::keyboard_controller::move_player;
+use bevy_test::keyboard_controller::process_key_event;
+use bevy_test::mouse_controller::check_mouse_click;
+use bevy_test::mouse_controller::update_cursor_position;
+use bevy_test::mouse_controller::CursorPosition;
+use bevy_test::player::spawn_player;
+use bevy_test::prefab::load_prefabs;
+use bevy_test::player::spawn_player_visual;
+use bevy_test::region::compact_settled_chunks;
+use bevy_test::region::evict_distant_chunks;
+use bevy_test::save::restore_world_save;
+use bevy_test::save::save_world;
+use bevy_test::seed::WorldSeed;
+use bevy_test::market::update_market;
+use bevy_test::market::update_money_text;
+use bevy_test::market::SaleEvent;
+use bevy_test::material_registry::MaterialRegistry;
+use bevy_test::materials::DefaultMaterial;
+use bevy_test::materials::GridMaterial;
+use bevy_test::terrain_config::load_terrain_config;
+use bevy_test::time_control::apply_time_scale;
+use bevy_test::time_control::simulation_tick_rate;
+use bevy_test::time_control::step_paused_simulation;
+use bevy_test::time_control::update_time_scale;
+use bevy_test::time_control::TimeScale;
+use bevy_test::tools::spawn_tool_visuals;
+use bevy_test::tools::spawn_tools;
+use bevy_test::tools::update_shovel_visual;
+use bevy_test::tools::update_tool;
+use bevy_test::util::arg_value;
+use bevy_test::util::has_arg;
+use bevy_test::world_generation::setup_camera;
+use bevy_test::world_generation::setup_world;
+use bevy_test::world_generation::grid_tick;
+use bevy_test::constants::WINDOW_WIDTH;
+use bevy_test::render::render;
+use bevy_test::render::TextureWritePlugin;
 
 fn main() {
+    configure_sizes(
+      arg_value("--chunk-size").map(|value| value.parse().expect("--chunk-size must be a number")).unwrap_or(DEFAULT_CHUNK_SIZE),
+      arg_value("--shadow-resolution").map(|value| value.parse().expect("--shadow-resolution must be a number")).unwrap_or(DEFAULT_SHADOW_RESOLUTION),
+    );
+    let registry = MaterialRegistry::load();
+    load_terrain_config(&registry);
+    load_prefabs(&registry);
+    let headless = has_arg("--headless");
     let mut app = App::new();
-    app.add_plugins(DefaultPlugins.set(WindowPlugin {
-            primary_window: Some(Window {
-                present_mode: PresentMode::AutoNoVsync,
-                title: "UwU".into(),
-                resolution: (WINDOW_WIDTH as f32, WINDOW_HEIGHT as f32).into(),
+    if headless {
+        app.add_plugins((MinimalPlugins, LogPlugin::default(), HeadlessPlugin));
+    } else {
+        app.add_plugins(DefaultPlugins.set(WindowPlugin {
+                primary_window: Some(Window {
+                    present_mode: PresentMode::AutoNoVsync,
+                    title: "UwU".into(),
+                    resolution: (WINDOW_WIDTH as f32, WINDOW_HEIGHT as f32).into(),
+                    ..default()
+                }),
                 ..default()
-            }),
-            ..default()
-        }))
-        .add_plugins((
-          FrameTimeDiagnosticsPlugin,
-          EntityCountDiagnosticsPlugin,
-          SystemInformationDiagnosticsPlugin,
-          Material2dPlugin::<GridMaterial>::default(),
-          Material2dPlugin::<DefaultMaterial>::default(),
-          ShadowsComputePlugin,
-          PerfUiPlugin,
-        ))
-        .edit_schedule(Startup, |schedule| {
+            }))
+            .add_plugins((
+              FrameTimeDiagnosticsPlugin,
+              EntityCountDiagnosticsPlugin,
+              SystemInformationDiagnosticsPlugin,
+              Material2dPlugin::<GridMaterial>::default(),
+              Material2dPlugin::<DefaultMaterial>::default(),
+              ShadowsComputePlugin,
+              TextureWritePlugin,
+              PerfUiPlugin,
+            ))
+            .insert_resource(ClearColor(Color::srgb(0.0, 0.0, 0.0)));
+    }
+    app.edit_schedule(Startup, |schedule| {
           schedule.set_build_settings(ScheduleBuildSettings {
               auto_insert_apply_deferred: false,
               ..default()
           });
         })
-        .insert_resource(ClearColor(Color::srgb(0.0, 0.0, 0.0)));
+        .init_resource::<CursorPosition>()
+        .insert_resource(registry)
+        .insert_resource(WorldSeed::from_args())
+        .insert_resource(Time::<Fixed>::from_hz(simulation_tick_rate()))
+        .insert_resource(TimeScale::from_args());
     if LIGHTING_DEMO {
       // app.add_systems(Startup, (setup_camera, setup_world, setup_timer).chain());
       app.add_systems(Update, (spawn_random_squares, render));
     } else {
-      app.add_event::<chunk_generator::NewChunkEvent>();
-      app.add_systems(Startup, (setup_camera, apply_deferred, build_compute_shader, apply_deferred, setup_world, spawn_player, apply_deferred, spawn_tools).chain());
-      app.add_systems(Update, (process_key_event, update_tool, check_mouse_click, grid_tick, render, generate_chunk_listener));
+      app.add_event::<NewChunkEvent>();
+      app.add_event::<SaleEvent>();
+      if headless {
+        app.add_systems(Startup, (setup_world, spawn_player, apply_deferred, spawn_tools, apply_deferred, restore_world_save).chain());
+      } else {
+        app.add_systems(Startup, (setup_camera, apply_deferred, build_compute_shader, apply_deferred, setup_world, spawn_player, apply_deferred, spawn_tools, apply_deferred, spawn_player_visual, spawn_tool_visuals, apply_deferred, restore_world_save).chain());
+        app.add_systems(Update, (update_cursor_position.before(update_tool).before(check_mouse_click), update_shovel_visual, update_money_text, render));
+        app.add_systems(Last, save_world);
+      }
+      app.add_systems(Update, (process_key_event, update_tool, check_mouse_click, undo_redo, update_market, generate_chunk_listener, collect_generated_chunks, evict_distant_chunks.run_if(on_timer(Duration::from_secs_f32(CHUNK_EVICTION_INTERVAL))), compact_settled_chunks.run_if(on_timer(Duration::from_secs_f32(CHUNK_COMPACTION_INTERVAL)))));
+      app.add_systems(Update, (update_time_scale, apply_time_scale, step_paused_simulation).chain());
+      app.add_systems(FixedUpdate, (move_player, grid_tick));
     }
     app.run();
 }
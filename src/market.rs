@@ -0,0 +1,106 @@
+use bevy::{ecs::event::{Event, EventReader}, prelude::{Local, Query, Res, ResMut, Resource, With}, text::Text, time::Time};
+use rand::{rngs::SmallRng, Rng};
+
+use crate::components::{Count, MoneyTextTag};
+use crate::constants::{MARKET_FLOOD_IMPACT, MARKET_MAX_DEPRESSION, MARKET_MAX_DRIFT, MARKET_MIN_DRIFT, MARKET_RECOVERY_RATE, MARKET_REVERSION, MARKET_VOLATILITY};
+use crate::material_registry::MaterialRegistry;
+use crate::seed::{chunk_rng, MARKET_STREAM};
+
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub struct SaleEvent {
+    pub material: u8,
+    pub quantity: u32,
+    pub price: f32,
+}
+
+impl SaleEvent {
+    pub fn total(&self) -> f32 {
+        self.price * self.quantity as f32
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MarketPrice {
+    pub base: f32,
+    pub drift: f32,
+    pub depression: f32,
+}
+
+#[derive(Resource, Debug)]
+pub struct Market {
+    pub prices: Vec<MarketPrice>,
+    rng: SmallRng,
+}
+
+impl Market {
+    pub fn new(registry: &MaterialRegistry, seed: u64) -> Market {
+        let prices = registry.materials.iter().map(|material| MarketPrice { base: material.sell_value, drift: 1., depression: 0. }).collect();
+        Market { prices, rng: chunk_rng(seed, 0, 0, MARKET_STREAM) }
+    }
+
+    pub fn price(&self, material: u8) -> f32 {
+        let price = &self.prices[material as usize];
+        price.base * price.drift * (1. - price.depression)
+    }
+
+    pub fn sell(&mut self, material: u8, quantity: u32) -> SaleEvent {
+        let sale = SaleEvent { material, quantity, price: self.price(material) };
+        let price = &mut self.prices[material as usize];
+        price.depression = (price.depression + quantity as f32 * MARKET_FLOOD_IMPACT).min(MARKET_MAX_DEPRESSION);
+        sale
+    }
+
+    pub fn tick(&mut self, seconds: f32) {
+        for price in self.prices.iter_mut() {
+            price.depression *= (-MARKET_RECOVERY_RATE * seconds).exp();
+            let shock = self.rng.random_range(-1.0..1.0) * MARKET_VOLATILITY * seconds.sqrt();
+            price.drift = (price.drift + (1. - price.drift) * MARKET_REVERSION * seconds + shock).clamp(MARKET_MIN_DRIFT, MARKET_MAX_DRIFT);
+        }
+    }
+}
+
+pub fn update_market(time: Res<Time>, mut market: ResMut<Market>) {
+    market.tick(time.delta_seconds());
+}
+
+pub fn update_money_text(
+    mut sale_events: EventReader<SaleEvent>,
+    mut money_text_query: Query<&mut Text, With<MoneyTextTag>>,
+    money_count_query: Query<&Count>,
+    registry: Res<MaterialRegistry>,
+    mut last_sale: Local<Option<SaleEvent>>,
+) {
+    if let Some(sale) = sale_events.read().last() {
+        *last_sale = Some(*sale);
+    }
+    let Ok(mut money_text) = money_text_query.get_single_mut() else {
+        return;
+    };
+    let money_count = money_count_query.get_single().unwrap();
+    money_text.sections[0].value = format!("${:.2} ", money_count.count);
+    if let Some(sale) = *last_sale {
+        money_text.sections[0].value += &format!("+{} {} at ${:.3}", sale.quantity, registry.get(sale.material).name, sale.price);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{color_map::{COPPER, SILVER}, constants::{MARKET_MAX_DRIFT, MARKET_MIN_DRIFT}, market::Market, material_registry::MaterialRegistry};
+
+    #[test]
+    fn test_flooding_depresses_price_until_it_recovers() {
+        let registry = MaterialRegistry::default();
+        let mut market = Market::new(&registry, 3);
+        let base = market.price(COPPER);
+        let sale = market.sell(COPPER, 200);
+        assert_eq!(sale.price, base);
+        assert!((sale.total() - base * 200.).abs() < 1e-4);
+        assert!(market.price(COPPER) < base * 0.7);
+        assert_eq!(market.price(SILVER), registry.get(SILVER).sell_value);
+        for _ in 0..600 {
+            market.tick(1.);
+        }
+        assert!(market.prices[COPPER as usize].depression < 0.01);
+        assert!(market.prices.iter().all(|price| (MARKET_MIN_DRIFT..=MARKET_MAX_DRIFT).contains(&price.drift)));
+    }
+}
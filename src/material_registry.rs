@@ -0,0 +1,276 @@
+use std::fs;
+use std::sync::OnceLock;
+
+use bevy::{math::Vec4, prelude::Resource};
+use bevy::log::info;
+use rand::Rng;
+use ron::extensions::Extensions;
+use serde::Deserialize;
+
+use crate::color_map::{inverse_gamma_correct, BLACK, CLEAR, COPPER, DIRT1, DIRT2, DIRT3, DRILL_BLACK, DRILL_GREY, GRASS1, GRASS2, GRAVEL1, GRAVEL2, GRAVEL3, ICE, LEAVES, LIGHT, PLAYER_SKIN, RED, REFINED_COPPER, ROCK, SAND, SANDSTONE, SELL_BOX, SILVER, SKY, SNOW, STEAM, STEEL, TALL_GRASS, TRANSLUCENT_GREY, TRUNK, WATER, WHITE, WOOD};
+use crate::constants::MATERIALS_PATH;
+use crate::seed::{pixel_rng, GRAVEL_STREAM};
+use crate::util::arg_value;
+
+const DEFAULT_MATERIALS: &str = include_str!("../assets/materials.ron");
+
+static MATERIAL_REGISTRY: OnceLock<MaterialRegistry> = OnceLock::new();
+
+const BUILTIN_MATERIALS: [(u8, &str); 34] = [
+    (SKY, "SKY"), (DIRT1, "DIRT1"), (DIRT2, "DIRT2"), (DIRT3, "DIRT3"), (COPPER, "COPPER"), (ROCK, "ROCK"), (GRAVEL1, "GRAVEL1"), (GRAVEL2, "GRAVEL2"),
+    (GRAVEL3, "GRAVEL3"), (LIGHT, "LIGHT"), (REFINED_COPPER, "REFINED_COPPER"), (SELL_BOX, "SELL_BOX"), (TRANSLUCENT_GREY, "TRANSLUCENT_GREY"), (CLEAR, "CLEAR"), (WHITE, "WHITE"), (RED, "RED"),
+    (STEEL, "STEEL"), (PLAYER_SKIN, "PLAYER_SKIN"), (BLACK, "BLACK"), (DRILL_BLACK, "DRILL_BLACK"), (DRILL_GREY, "DRILL_GREY"), (SILVER, "SILVER"), (GRASS1, "GRASS1"), (GRASS2, "GRASS2"),
+    (SAND, "SAND"), (SANDSTONE, "SANDSTONE"), (SNOW, "SNOW"), (ICE, "ICE"), (WOOD, "WOOD"), (TRUNK, "TRUNK"), (LEAVES, "LEAVES"), (TALL_GRASS, "TALL_GRASS"),
+    (WATER, "WATER"), (STEAM, "STEAM"),
+];
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum Gravity {
+    Static,
+    Falls,
+    Liquid,
+    Rises,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Slide {
+    pub drop: i32,
+    pub run: i32,
+}
+
+impl Slide {
+    fn from_repose(repose: f32) -> Result<Slide, String> {
+        if repose <= 0. || repose >= 90. {
+            return Err(format!("angle of repose {} must be between 0 and 90 degrees", repose));
+        }
+        let slope = repose.to_radians().tan();
+        if slope >= 1. {
+            Ok(Slide { drop: slope.round() as i32, run: 1 })
+        } else {
+            Ok(Slide { drop: 1, run: (1. / slope).round() as i32 })
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+pub enum ShovelAction {
+    #[default]
+    Ignore,
+    Collect,
+    CollectAs(String),
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "Material")]
+struct MaterialFile {
+    name: String,
+    color: (u8, u8, u8, u8),
+    solid: bool,
+    gravity: Gravity,
+    #[serde(default)]
+    repose: Option<f32>,
+    #[serde(default)]
+    shovel: ShovelAction,
+    #[serde(default)]
+    pickaxe: Vec<(String, u32)>,
+    hardness: f32,
+    density: f32,
+    #[serde(default)]
+    sell_value: f32,
+    #[serde(default)]
+    replaceable: bool,
+    #[serde(default)]
+    ore_host: bool,
+    #[serde(default)]
+    sells_on_contact: bool,
+    #[serde(default)]
+    fertile: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct MaterialProperties {
+    pub name: String,
+    pub color: [u8; 4],
+    pub solid: bool,
+    pub gravity: Gravity,
+    pub slide: Option<Slide>,
+    pub shovel: Option<u8>,
+    pub pickaxe: Vec<u8>,
+    pub hardness: f32,
+    pub density: f32,
+    pub sell_value: f32,
+    pub replaceable: bool,
+    pub ore_host: bool,
+    pub sells_on_contact: bool,
+    pub fertile: bool,
+}
+
+#[derive(Resource, Clone, Debug)]
+pub struct MaterialRegistry {
+    pub materials: Vec<MaterialProperties>,
+}
+
+impl MaterialRegistry {
+    pub fn parse(source: &str) -> Result<MaterialRegistry, String> {
+        let files: Vec<MaterialFile> = ron::Options::default().with_default_extension(Extensions::IMPLICIT_SOME).from_str(source).map_err(|err| err.to_string())?;
+        if files.len() > u8::MAX as usize + 1 {
+            return Err(format!("at most {} materials fit in a pixel, found {}", u8::MAX as usize + 1, files.len()));
+        }
+        for (id, name) in BUILTIN_MATERIALS {
+            match files.get(id as usize) {
+                Some(file) if file.name == name => {},
+                Some(file) => return Err(format!("material {} must be {}, found {}", id, name, file.name)),
+                None => return Err(format!("material {} ({}) is missing", id, name)),
+            }
+        }
+        let by_name = |name: &str| files.iter().position(|file| file.name == name).map(|id| id as u8).ok_or_else(|| format!("unknown material {}", name));
+        let mut materials = Vec::with_capacity(files.len());
+        for (id, file) in files.iter().enumerate() {
+            if files[..id].iter().any(|other| other.name == file.name) {
+                return Err(format!("material {} is defined twice", file.name));
+            }
+            let shovel = match &file.shovel {
+                ShovelAction::Ignore => None,
+                ShovelAction::Collect => Some(id as u8),
+                ShovelAction::CollectAs(name) => Some(by_name(name)?),
+            };
+            let mut pickaxe = Vec::new();
+            for (name, weight) in file.pickaxe.iter() {
+                pickaxe.extend(std::iter::repeat_n(by_name(name)?, *weight as usize));
+            }
+            let slide = file.repose.map(Slide::from_repose).transpose().map_err(|err| format!("{}: {}", file.name, err))?;
+            let (r, g, b, a) = file.color;
+            materials.push(MaterialProperties {
+                name: file.name.clone(),
+                color: [r, g, b, a],
+                solid: file.solid,
+                gravity: file.gravity,
+                slide,
+                shovel,
+                pickaxe,
+                hardness: file.hardness,
+                density: file.density,
+                sell_value: file.sell_value,
+                replaceable: file.replaceable,
+                ore_host: file.ore_host,
+                sells_on_contact: file.sells_on_contact,
+                fertile: file.fertile,
+            });
+        }
+        Ok(MaterialRegistry { materials })
+    }
+
+    pub fn load() -> MaterialRegistry {
+        let registry = match arg_value("--materials") {
+            Some(path) => {
+                let source = fs::read_to_string(&path).unwrap_or_else(|err| panic!("Could not read materials {}: {}", path, err));
+                MaterialRegistry::parse(&source).unwrap_or_else(|err| panic!("Could not parse materials {}: {}", path, err))
+            },
+            None => match fs::read_to_string(MATERIALS_PATH) {
+                Ok(source) => MaterialRegistry::parse(&source).unwrap_or_else(|err| panic!("Could not parse materials {}: {}", MATERIALS_PATH, err)),
+                Err(_) => {
+                    info!("No materials at {}, using the built in materials", MATERIALS_PATH);
+                    MaterialRegistry::default()
+                },
+            },
+        };
+        info!("Loaded {} materials", registry.materials.len());
+        if MATERIAL_REGISTRY.set(registry.clone()).is_err() {
+            panic!("Materials must be loaded before they are first read");
+        }
+        registry
+    }
+
+    pub fn get(&self, material: u8) -> &MaterialProperties {
+        &self.materials[material as usize]
+    }
+
+    pub fn by_name(&self, name: &str) -> Option<u8> {
+        self.materials.iter().position(|material| material.name == name).map(|material| material as u8)
+    }
+
+    pub fn is_solid(&self, material: u8) -> bool {
+        self.get(material).solid
+    }
+
+    pub fn falls(&self, material: u8) -> bool {
+        matches!(self.get(material).gravity, Gravity::Falls | Gravity::Liquid)
+    }
+
+    pub fn is_liquid(&self, material: u8) -> bool {
+        self.get(material).gravity == Gravity::Liquid
+    }
+
+    pub fn is_replaceable(&self, material: u8) -> bool {
+        self.get(material).replaceable
+    }
+
+    pub fn rises(&self, material: u8) -> bool {
+        self.get(material).gravity == Gravity::Rises
+    }
+
+    pub fn moves(&self, material: u8) -> bool {
+        self.get(material).gravity != Gravity::Static
+    }
+
+    fn can_be_displaced(&self, material: u8) -> bool {
+        !self.is_solid(material) && (self.is_replaceable(material) || self.moves(material))
+    }
+
+    pub fn sinks_through(&self, material: u8, other: u8) -> bool {
+        self.falls(material) && self.can_be_displaced(other) && self.get(material).density > self.get(other).density
+    }
+
+    pub fn floats_through(&self, material: u8, other: u8) -> bool {
+        self.rises(material) && self.can_be_displaced(other) && self.get(material).density < self.get(other).density
+    }
+
+    pub fn pickaxe_result(&self, material: u8, seed: u64, x_g: i32, y_g: i32) -> Option<u8> {
+        let palette = &self.get(material).pickaxe;
+        if palette.is_empty() {
+            return None;
+        }
+        Some(palette[pixel_rng(seed, x_g, y_g, GRAVEL_STREAM).random_range(0..palette.len())])
+    }
+
+    pub fn decoder(&self) -> Vec<Vec4> {
+        self.materials.iter().map(|material| {
+            let [r, g, b, a] = material.color.map(|channel| channel as f32 / 255.);
+            Vec4::new(inverse_gamma_correct(r), inverse_gamma_correct(g), inverse_gamma_correct(b), a)
+        }).collect()
+    }
+}
+
+// For code that runs outside the ECS, such as chunk generation on worker threads
+pub fn material_registry() -> &'static MaterialRegistry {
+    MATERIAL_REGISTRY.get_or_init(MaterialRegistry::default)
+}
+
+impl Default for MaterialRegistry {
+    fn default() -> MaterialRegistry {
+        MaterialRegistry::parse(DEFAULT_MATERIALS).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{color_map::{DIRT1, GRASS2, GRAVEL1, GRAVEL2, GRAVEL3, LIGHT, ROCK, SAND, SELL_BOX, SKY, STEAM, TRUNK, WATER, WOOD}, material_registry::MaterialRegistry};
+
+    #[test]
+    fn test_material_registry() {
+        let registry = MaterialRegistry::default();
+        assert_eq!(registry.get(TRUNK).shovel, Some(WOOD));
+        assert!(registry.falls(TRUNK) && !registry.falls(ROCK));
+        assert!(!registry.is_solid(SKY));
+        assert!(registry.is_replaceable(SKY) && registry.is_replaceable(LIGHT) && !registry.is_replaceable(DIRT1));
+        assert!(registry.get(ROCK).ore_host && !registry.get(SAND).ore_host);
+        assert!(registry.get(SELL_BOX).sells_on_contact && registry.get(GRASS2).fertile);
+        assert!(registry.sinks_through(GRAVEL1, WATER) && !registry.sinks_through(WATER, GRAVEL1) && !registry.sinks_through(GRAVEL1, ROCK));
+        assert!(registry.floats_through(STEAM, SKY) && registry.floats_through(STEAM, WATER) && !registry.sinks_through(TRUNK, WATER));
+        assert!((0..50).all(|x_g| matches!(registry.pickaxe_result(ROCK, 7, x_g, 0), Some(GRAVEL1 | GRAVEL2 | GRAVEL3))));
+        assert_eq!(registry.pickaxe_result(SKY, 7, 0, 0), None);
+        assert_eq!(registry.decoder().len(), registry.materials.len());
+        let swapped = include_str!("../assets/materials.ron").replacen("\"DIRT1\"", "\"MUD\"", 1);
+        assert!(MaterialRegistry::parse(&swapped).is_err());
+    }
+}
@@ -1,21 +1,37 @@
 
-use bevy::{asset::{Asset, Handle}, ecs::component::Component, math::{Vec2, Vec4}, prelude::Image, render::render_resource::{AsBindGroup, Buffer, ShaderRef}, sprite::Material2d};
+use bevy::{asset::{Asset, Handle}, ecs::component::Component, math::{Vec2, Vec4}, prelude::Image, render::{mesh::MeshVertexBufferLayoutRef, render_resource::{AsBindGroup, Buffer, RenderPipelineDescriptor, ShaderDefVal, ShaderRef, SpecializedMeshPipelineError}}, sprite::{Material2d, Material2dKey}};
 use bevy_reflect::TypePath;
 
+use crate::constants::{chunk_size, shadow_resolution};
+
+pub fn size_shader_defs() -> Vec<ShaderDefVal> {
+    vec![
+        ShaderDefVal::UInt("CHUNK_SIZE".into(), chunk_size() as u32),
+        ShaderDefVal::UInt("SHADOW_RESOLUTION".into(), shadow_resolution() as u32),
+    ]
+}
+
 #[derive(Asset, TypePath, AsBindGroup, Debug, Clone, Component)]
 pub struct DefaultMaterial {
     #[uniform(0)]
     pub size: Vec2,
     #[texture(1)]
     pub color_map_handle: Handle<Image>,
-    #[uniform(2)]
-    pub decoder: [Vec4; 24],
+    #[storage(2, read_only)]
+    pub decoder: Vec<Vec4>,
 }
 
 impl Material2d for DefaultMaterial {
     fn fragment_shader() -> ShaderRef {
         "shaders/default_texture_shader.wgsl".into()
     }
+
+    fn specialize(descriptor: &mut RenderPipelineDescriptor, _layout: &MeshVertexBufferLayoutRef, _key: Material2dKey<Self>) -> Result<(), SpecializedMeshPipelineError> {
+        if let Some(fragment) = descriptor.fragment.as_mut() {
+            fragment.shader_defs.extend(size_shader_defs());
+        }
+        Ok(())
+    }
 }
 
 #[derive(Asset, TypePath, AsBindGroup, Debug, Clone, Component)]
@@ -24,11 +40,14 @@ pub struct GridMaterial {
     pub size: Vec2,
     #[texture(1)]
     pub color_map_handle: Handle<Image>,
-    #[uniform(2)]
-    pub decoder: [Vec4; 24],
+    #[storage(2, read_only)]
+    pub decoder: Vec<Vec4>,
+    #[texture(3)]
+    pub damage_map_handle: Handle<Image>,
     #[uniform(5)]
     pub global_chunk_pos: Vec2,
     pub on_screen_chunk_position: [i8; 2],
+    pub displayed_chunk: Option<(i32, i32)>,
     #[uniform(6)]
     pub player_pos: Vec2,
     #[storage(4, read_only, buffer)]
@@ -39,4 +58,11 @@ impl Material2d for GridMaterial {
     fn fragment_shader() -> ShaderRef {
         "shaders/render_shader.wgsl".into()
     }
+
+    fn specialize(descriptor: &mut RenderPipelineDescriptor, _layout: &MeshVertexBufferLayoutRef, _key: Material2dKey<Self>) -> Result<(), SpecializedMeshPipelineError> {
+        if let Some(fragment) = descriptor.fragment.as_mut() {
+            fragment.shader_defs.extend(size_shader_defs());
+        }
+        Ok(())
+    }
 }
\ No newline at end of file
@@ -0,0 +1,141 @@
+use rand::Rng;
+
+use crate::color_map::{COPPER, SILVER};
+use crate::constants::{chunk_size, COPPER_MIN_DEPTH, COPPER_SPAWN_RADIUS, COPPER_VEIN_FILL, DEFAULT_CHUNK_SIZE, MAX_COPPER_ORE_SPAWNS, MAX_SILVER_ORE_SPAWNS, ORE_FULL_DENSITY_DEPTH, SILVER_MIN_DEPTH, SILVER_SPAWN_RADIUS, SILVER_VEIN_FILL};
+use crate::material_registry::material_registry;
+use crate::seed::{chunk_rng, coordinate_hash, COPPER_STREAM, SILVER_STREAM};
+use crate::util::{get_global_x_coordinate, get_global_y_coordinate};
+
+
+struct OreSpec {
+    ore: u8,
+    stream: u64,
+    max_spawns: usize,
+    max_radius: i32,
+    min_depth: f32,
+    fill: f32,
+}
+
+const ORE_SPECS: [OreSpec; 2] = [
+    OreSpec { ore: COPPER, stream: COPPER_STREAM, max_spawns: MAX_COPPER_ORE_SPAWNS, max_radius: COPPER_SPAWN_RADIUS, min_depth: COPPER_MIN_DEPTH, fill: COPPER_VEIN_FILL },
+    OreSpec { ore: SILVER, stream: SILVER_STREAM, max_spawns: MAX_SILVER_ORE_SPAWNS, max_radius: SILVER_SPAWN_RADIUS, min_depth: SILVER_MIN_DEPTH, fill: SILVER_VEIN_FILL },
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct OreVein {
+    pub ore: u8,
+    pub stream: u64,
+    pub center_x_g: i32,
+    pub center_y_g: i32,
+    pub radius_x: i32,
+    pub radius_y: i32,
+    pub fill: f32,
+}
+
+impl OreVein {
+    pub fn claims(&self, seed: u64, x_g: i32, y_g: i32) -> bool {
+        let dx = (x_g - self.center_x_g) as f32 / self.radius_x as f32;
+        let dy = (y_g - self.center_y_g) as f32 / self.radius_y as f32;
+        let falloff = 1. - (dx * dx + dy * dy);
+        if falloff <= 0. {
+            return false;
+        }
+        let roll = coordinate_hash(seed, x_g, y_g, self.stream) as f64 / u64::MAX as f64;
+        roll < (self.fill * falloff) as f64
+    }
+}
+
+fn depth_factor(y_g: i32, min_depth: f32) -> f32 {
+    ((-y_g as f32 - min_depth) / ORE_FULL_DENSITY_DEPTH).clamp(0., 1.)
+}
+
+pub fn ore_veins_in_chunk(seed: u64, chunk_x_g: i32, chunk_y_g: i32) -> Vec<OreVein> {
+    let mut veins = Vec::new();
+    let area_scale = chunk_size() * chunk_size() / (DEFAULT_CHUNK_SIZE * DEFAULT_CHUNK_SIZE);
+    for spec in ORE_SPECS.iter() {
+        let chunk_depth = depth_factor(chunk_y_g * chunk_size() as i32, spec.min_depth);
+        if chunk_depth == 0. {
+            continue;
+        }
+        let mut rng = chunk_rng(seed, chunk_x_g, chunk_y_g, spec.stream);
+        let count = (spec.max_spawns as f32 * chunk_depth * area_scale * rng.random::<f32>()).round() as usize;
+        for _ in 0..count {
+            let center_x_g = get_global_x_coordinate(chunk_x_g, rng.random_range(0..chunk_size() as usize));
+            let center_y_g = get_global_y_coordinate(chunk_y_g, rng.random_range(0..chunk_size() as usize));
+            let size = 0.4 + 0.6 * depth_factor(center_y_g, spec.min_depth);
+            veins.push(OreVein {
+                ore: spec.ore,
+                stream: spec.stream,
+                center_x_g,
+                center_y_g,
+                radius_x: ((spec.max_radius as f32 * size * rng.random_range(0.5..=1.)) as i32).max(1),
+                radius_y: ((spec.max_radius as f32 * size * rng.random_range(0.5..=1.)) as i32).max(1),
+                fill: spec.fill,
+            });
+        }
+    }
+    veins
+}
+
+pub fn place_ore_veins(grid: &mut [u8], chunk_x_g: i32, chunk_y_g: i32, seed: u64) {
+    let max_radius = ORE_SPECS.iter().map(|spec| spec.max_radius).max().unwrap();
+    let reach = (max_radius as f32 / chunk_size()).ceil() as i32;
+    let left_x_g = get_global_x_coordinate(chunk_x_g, 0);
+    let top_y_g = get_global_y_coordinate(chunk_y_g, 0);
+    for source_x_g in chunk_x_g - reach..=chunk_x_g + reach {
+        for source_y_g in chunk_y_g - reach..=chunk_y_g + reach {
+            for vein in ore_veins_in_chunk(seed, source_x_g, source_y_g) {
+                let min_x = (vein.center_x_g - vein.radius_x - left_x_g).max(0);
+                let max_x = (vein.center_x_g + vein.radius_x - left_x_g).min(chunk_size() as i32 - 1);
+                let min_y = (top_y_g - vein.center_y_g - vein.radius_y).max(0);
+                let max_y = (top_y_g - vein.center_y_g + vein.radius_y).min(chunk_size() as i32 - 1);
+                for y in min_y..=max_y {
+                    for x in min_x..=max_x {
+                        let index = y as usize * chunk_size() as usize + x as usize;
+                        if material_registry().get(grid[index]).ore_host && vein.claims(seed, left_x_g + x, top_y_g - y) {
+                            grid[index] = vein.ore;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{color_map::{COPPER, DIRT1, SILVER}, constants::chunk_size, ore::{ore_veins_in_chunk, place_ore_veins}, util::{get_global_x_coordinate, get_global_y_coordinate}};
+
+    fn ore_chunk(chunk_x_g: i32, chunk_y_g: i32, seed: u64) -> Vec<u8> {
+        let mut grid = vec![DIRT1; (chunk_size() * chunk_size()) as usize];
+        place_ore_veins(&mut grid, chunk_x_g, chunk_y_g, seed);
+        grid
+    }
+
+    #[test]
+    fn test_veins_cross_chunk_boundaries() {
+        let (left, right) = ((0, -4), (1, -4));
+        let (edge_x_g, top_y_g) = (get_global_x_coordinate(right.0, 0), get_global_y_coordinate(right.1, 0));
+        let seed = (0..).find(|seed| ore_veins_in_chunk(*seed, left.0, left.1).iter().any(|vein| vein.center_x_g + vein.radius_x / 2 >= edge_x_g)).unwrap();
+        let right_grid = ore_chunk(right.0, right.1, seed);
+        let mut crossing_pixels = 0;
+        for vein in ore_veins_in_chunk(seed, left.0, left.1) {
+            for y in 0..chunk_size() as i32 {
+                for x in 0..vein.radius_x {
+                    if vein.claims(seed, edge_x_g + x, top_y_g - y) {
+                        assert!(matches!(right_grid[(y * chunk_size() as i32 + x) as usize], COPPER | SILVER));
+                        crossing_pixels += 1;
+                    }
+                }
+            }
+        }
+        assert!(crossing_pixels > 0);
+    }
+
+    #[test]
+    fn test_ore_increases_with_depth() {
+        let count_ore = |chunk_y_g: i32| (-2..=2).map(|chunk_x_g| ore_chunk(chunk_x_g, chunk_y_g, 3).iter().filter(|pixel| **pixel == COPPER || **pixel == SILVER).count()).sum::<usize>();
+        assert_eq!(count_ore(1), 0);
+        assert!(count_ore(-1) < count_ore(-5));
+    }
+}
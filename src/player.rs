@@ -1,33 +1,36 @@
-use std::collections::HashMap;
+use bevy::{asset::Assets, math::{Vec2, Vec3}, prelude::{Commands, Entity, Image, Mesh, Query, Rectangle, Res, ResMut, SpatialBundle, Transform, With}, sprite::Mesh2dHandle, time::Time};
 
-use bevy::{asset::Assets, math::{Vec2, Vec3}, prelude::{Commands, Image, Mesh, Rectangle, Res, ResMut, Transform}, sprite::MaterialMesh2dBundle, time::Time};
+use crate::{color_map::{BLACK, PLAYER_SKIN, RED, WHITE}, components::{ChunkLoader, ChunkMap, PlayerTag, Velocity}, constants::{CHUNK_LOAD_RADIUS, LIQUID_DRAG, MAX_STEP_HEIGHT, NO_GRAVITY, PLAYER_HEIGHT, PLAYER_SPAWN_X, PLAYER_SPAWN_Y, PLAYER_WIDTH}, material_registry::MaterialRegistry, materials::DefaultMaterial, tools::{CurrentTool, Tool}, util::{flatten_index_standard_grid, grid_to_image}};
 
-use crate::{color_map::{apply_gamma_correction, BLACK, LIGHT, PLAYER_SKIN, RAW_DECODER_DATA, RED, SELL_BOX, SKY, WHITE}, components::{PlayerTag, Velocity}, constants::{CHUNK_SIZE, MAX_STEP_HEIGHT, NO_GRAVITY, PLAYER_HEIGHT, PLAYER_SPAWN_X, PLAYER_SPAWN_Y, PLAYER_WIDTH}, materials::DefaultMaterial, tools::{CurrentTool, Tool}, util::{flatten_index_standard_grid, get_chunk_x_g, get_chunk_y_g, get_local_x, get_local_y, grid_to_image}};
-
-pub fn spawn_player(
-    mut commands: Commands,
-    mut materials: ResMut<Assets<DefaultMaterial>>,
-    mut images: ResMut<Assets<Image>>,
-    mut meshes: ResMut<Assets<Mesh>>,
-) {
+pub fn spawn_player(mut commands: Commands) {
     commands.spawn(PlayerTag)
             .insert(Velocity { vx: 0.0, vy: 0.0})
-            .insert(MaterialMesh2dBundle {
-                material: materials.add(DefaultMaterial {
-                    color_map_handle: images.add(generate_player_image()),
-                    size: Vec2::new(PLAYER_WIDTH as f32, PLAYER_HEIGHT as f32),
-                    decoder: apply_gamma_correction(RAW_DECODER_DATA),
-                }),
-                mesh: meshes
-                .add(Rectangle {
-                    half_size: Vec2::new((PLAYER_WIDTH/2) as f32, (PLAYER_HEIGHT/2) as f32),
-                })
-                .into(),
+            .insert(ChunkLoader { radius: CHUNK_LOAD_RADIUS })
+            .insert(SpatialBundle {
                 transform: Transform { translation: Vec3::new(PLAYER_SPAWN_X as f32, PLAYER_SPAWN_Y as f32, -1.), ..Default::default() },
                 ..Default::default()
             })
             .insert(CurrentTool { tool: Tool::Shovel });
-    
+}
+
+pub fn spawn_player_visual(
+    mut commands: Commands,
+    player_query: Query<Entity, With<PlayerTag>>,
+    mut materials: ResMut<Assets<DefaultMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    registry: Res<MaterialRegistry>,
+) {
+    let player = player_query.get_single().unwrap();
+    commands.entity(player)
+            .insert(materials.add(DefaultMaterial {
+                color_map_handle: images.add(generate_player_image()),
+                size: Vec2::new(PLAYER_WIDTH as f32, PLAYER_HEIGHT as f32),
+                decoder: registry.decoder(),
+            }))
+            .insert(Mesh2dHandle(meshes.add(Rectangle {
+                half_size: Vec2::new((PLAYER_WIDTH/2) as f32, (PLAYER_HEIGHT/2) as f32),
+            })));
 }
 
 pub fn generate_player_image() -> Image{
@@ -56,13 +59,18 @@ pub fn generate_player_image() -> Image{
     grid_to_image(&mut data_buffer, PLAYER_WIDTH as u32, PLAYER_HEIGHT as u32, None)
 }
 
+pub fn is_submerged(chunk_map: &ChunkMap, entity_position_c: &Vec3, registry: &MaterialRegistry) -> bool {
+    chunk_map.get(entity_position_c.x as i32, entity_position_c.y as i32).is_some_and(|pixel| registry.is_liquid(pixel))
+}
+
 pub fn apply_velocity(
     entity_position_c: &mut Vec3,
     velocity: &mut Velocity,
-    chunk_map: &HashMap<(i32, i32), Vec<u8>>,
+    chunk_map: &ChunkMap,
     time: &Res<Time>,
+    registry: &MaterialRegistry,
 ) {
-    let horizontal_collision = horizontal_collision(&velocity.vx, chunk_map, entity_position_c);
+    let horizontal_collision = horizontal_collision(&velocity.vx, chunk_map, entity_position_c, registry);
     if velocity.vx != 0. && horizontal_collision.0 {
         if horizontal_collision.1 as i32 <= MAX_STEP_HEIGHT {
             entity_position_c.y += horizontal_collision.1;
@@ -70,38 +78,39 @@ pub fn apply_velocity(
             velocity.vx = 0.;
         }
     }
-    if velocity.vy > 0. && vertical_collision(chunk_map, &entity_position_c) {
+    if velocity.vy > 0. && vertical_collision(chunk_map, &entity_position_c, registry) {
         velocity.vy = 0.;
     }
+    if is_submerged(chunk_map, entity_position_c, registry) {
+        let drag = (-LIQUID_DRAG * time.delta_seconds()).exp();
+        velocity.vx *= drag;
+        velocity.vy *= drag;
+    }
     entity_position_c.x += velocity.vx * time.delta_seconds();
     if !NO_GRAVITY {
         entity_position_c.y += velocity.vy * time.delta_seconds();
     }
 }
 
-fn horizontal_collision(velocity: &f32, chunk_map: &HashMap<(i32, i32), Vec<u8>>, entity_position_c: &Vec3) -> (bool, f32) {
+fn horizontal_collision(velocity: &f32, chunk_map: &ChunkMap, entity_position_c: &Vec3, registry: &MaterialRegistry) -> (bool, f32) {
     let mut max_height_found = 0.;
     let mut flag = false;
     if velocity < &0. || velocity > &0. {
         for y in entity_position_c.y as i32 - PLAYER_HEIGHT as i32/2 + 1..entity_position_c.y as i32 + PLAYER_HEIGHT as i32/2 {
-            let chunk_x_g: i32;
-            let local_x: usize;
-            if velocity < &0. {
-                chunk_x_g = get_chunk_x_g(entity_position_c.x as i32 - PLAYER_WIDTH as i32 / 2);
-                local_x = get_local_x((entity_position_c.x - PLAYER_WIDTH as f32/2.) as i32);
+            let x = if velocity < &0. {
+                (entity_position_c.x - PLAYER_WIDTH as f32/2.) as i32
             } else {
-                chunk_x_g = get_chunk_x_g(entity_position_c.x as i32 + PLAYER_WIDTH as i32 / 2);
-                local_x = get_local_x((entity_position_c.x + PLAYER_WIDTH as f32/2.) as i32);
-            }
-            let chunk_y_g = get_chunk_y_g(y);
-            let local_y = get_local_y(y);
-            let local_index = flatten_index_standard_grid(&local_x, &local_y, CHUNK_SIZE as usize);
-            if chunk_map.get(&(chunk_x_g, chunk_y_g)).unwrap()[local_index] != SKY && chunk_map.get(&(chunk_x_g, chunk_y_g)).unwrap()[local_index] != SELL_BOX {
-                max_height_found += 1.;
-                if y > MAX_STEP_HEIGHT + (entity_position_c.y as i32 - PLAYER_HEIGHT as i32/2 + 1) as i32 {
-                    return (true, max_height_found);
+                (entity_position_c.x + PLAYER_WIDTH as f32/2.) as i32
+            };
+            match chunk_map.get(x, y) {
+                Some(pixel) if !registry.is_solid(pixel) => {},
+                _ => {
+                    max_height_found += 1.;
+                    if y > MAX_STEP_HEIGHT + (entity_position_c.y as i32 - PLAYER_HEIGHT as i32/2 + 1) as i32 {
+                        return (true, max_height_found);
+                    }
+                    flag = true;
                 }
-                flag = true;
             }
         }
     }
@@ -111,16 +120,12 @@ fn horizontal_collision(velocity: &f32, chunk_map: &HashMap<(i32, i32), Vec<u8>>
     (false, max_height_found)
 }
 
-fn vertical_collision(chunk_map: &HashMap<(i32, i32), Vec<u8>>, entity_position_c: &Vec3) -> bool {
+fn vertical_collision(chunk_map: &ChunkMap, entity_position_c: &Vec3, registry: &MaterialRegistry) -> bool {
     for x in entity_position_c.x as i32 - PLAYER_WIDTH as i32 /2..entity_position_c.x as i32 + PLAYER_WIDTH as i32 /2 {
-        let chunk_x_g = get_chunk_x_g(x);
-        let local_x = get_local_x(x);
-        let chunk_y_g = get_chunk_y_g(entity_position_c.y as i32 + PLAYER_HEIGHT as i32 / 2 + 1);
-        let local_y = get_local_y((entity_position_c.y + PLAYER_HEIGHT as f32 / 2. + 1.) as i32);
-        let local_index = flatten_index_standard_grid(&local_x, &local_y, CHUNK_SIZE as usize);
-        if chunk_map.get(&(chunk_x_g, chunk_y_g)).unwrap()[local_index] != SKY && chunk_map.get(&(chunk_x_g, chunk_y_g)).unwrap()[local_index] != SELL_BOX && chunk_map.get(&(chunk_x_g, chunk_y_g)).unwrap()[local_index] != LIGHT {
-            return true
+        match chunk_map.get(x, (entity_position_c.y + PLAYER_HEIGHT as f32 / 2. + 1.) as i32) {
+            Some(pixel) if !registry.is_solid(pixel) => {},
+            _ => return true,
         }
     }
     false
-}
\ No newline at end of file
+}
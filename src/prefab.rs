@@ -0,0 +1,215 @@
+use std::collections::HashMap;
+use std::fs;
+use std::sync::OnceLock;
+
+use bevy::log::info;
+use noise::Perlin;
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::biome::surfaces_under;
+use crate::constants::{chunk_size, PREFAB_DIR, PREFAB_MAX_SURFACE_RANGE};
+use crate::material_registry::MaterialRegistry;
+use crate::seed::{chunk_rng, BURIED_PREFAB_STREAM, SURFACE_PREFAB_STREAM};
+use crate::util::{get_global_x_coordinate, get_global_y_coordinate};
+
+const DEFAULT_PREFABS: [(&str, &str); 3] = [
+    ("cabin", include_str!("../assets/prefabs/cabin.ron")),
+    ("mine_shaft", include_str!("../assets/prefabs/mine_shaft.ron")),
+    ("ruin", include_str!("../assets/prefabs/ruin.ron")),
+];
+
+static PREFABS: OnceLock<Vec<Prefab>> = OnceLock::new();
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum Placement {
+    Surface { sink: i32 },
+    Buried { min_depth: i32 },
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "Prefab")]
+struct PrefabFile {
+    placement: Placement,
+    chance: f32,
+    legend: HashMap<char, String>,
+    rows: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct Prefab {
+    pub name: String,
+    pub placement: Placement,
+    pub chance: f32,
+    pub width: usize,
+    pub height: usize,
+    pub cells: Vec<Option<u8>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PrefabPlacement {
+    pub prefab: usize,
+    pub left_x_g: i32,
+    pub top_y_g: i32,
+}
+
+pub fn parse_prefab(name: &str, source: &str, registry: &MaterialRegistry) -> Result<Prefab, String> {
+    let file: PrefabFile = ron::from_str(source).map_err(|err| err.to_string())?;
+    let width = file.rows.first().map(|row| row.chars().count()).unwrap_or(0);
+    if width == 0 || file.rows.iter().any(|row| row.chars().count() != width) {
+        return Err("rows must be non-empty and all the same width".to_string());
+    }
+    let mut legend = HashMap::new();
+    for (symbol, material_name) in file.legend.iter() {
+        legend.insert(*symbol, registry.by_name(material_name).ok_or_else(|| format!("unknown material {} for '{}'", material_name, symbol))?);
+    }
+    let mut cells = Vec::with_capacity(width * file.rows.len());
+    for symbol in file.rows.iter().flat_map(|row| row.chars()) {
+        match (symbol, legend.get(&symbol)) {
+            (_, Some(material)) => cells.push(Some(*material)),
+            ('.', None) => cells.push(None),
+            _ => return Err(format!("'{}' is not in the legend", symbol)),
+        }
+    }
+    Ok(Prefab { name: name.to_string(), placement: file.placement, chance: file.chance, width, height: file.rows.len(), cells })
+}
+
+pub fn load_prefabs(registry: &MaterialRegistry) {
+    let mut loaded = Vec::new();
+    match fs::read_dir(PREFAB_DIR) {
+        Ok(entries) => {
+            let mut paths: Vec<_> = entries.filter_map(|entry| entry.ok().map(|entry| entry.path())).filter(|path| path.extension().is_some_and(|extension| extension == "ron")).collect();
+            paths.sort();
+            for path in paths {
+                let source = fs::read_to_string(&path).unwrap_or_else(|err| panic!("Could not read prefab {}: {}", path.display(), err));
+                let name = path.file_stem().unwrap().to_string_lossy();
+                loaded.push(parse_prefab(&name, &source, registry).unwrap_or_else(|err| panic!("Could not parse prefab {}: {}", path.display(), err)));
+            }
+        },
+        Err(_) => {
+            info!("No prefab directory at {}, using the built in prefabs", PREFAB_DIR);
+            loaded = default_prefabs(registry);
+        },
+    }
+    info!("Loaded {} prefabs", loaded.len());
+    if PREFABS.set(loaded).is_err() {
+        panic!("Prefabs must be loaded before they are first read");
+    }
+}
+
+fn default_prefabs(registry: &MaterialRegistry) -> Vec<Prefab> {
+    DEFAULT_PREFABS.iter().map(|(name, source)| parse_prefab(name, source, registry).unwrap()).collect()
+}
+
+pub fn prefabs() -> &'static Vec<Prefab> {
+    PREFABS.get_or_init(|| default_prefabs(&MaterialRegistry::default()))
+}
+
+pub fn surface_placements(seed: u64, chunk_x_g: i32, perlin: &Perlin) -> Vec<PrefabPlacement> {
+    let mut placements = Vec::new();
+    let mut rng = chunk_rng(seed, chunk_x_g, 0, SURFACE_PREFAB_STREAM);
+    for (index, prefab) in prefabs().iter().enumerate() {
+        let Placement::Surface { sink } = prefab.placement else {
+            continue;
+        };
+        let (roll, x) = (rng.random::<f32>(), rng.random_range(0..chunk_size() as usize));
+        if roll >= prefab.chance {
+            continue;
+        }
+        let left_x_g = get_global_x_coordinate(chunk_x_g, x);
+        let (low, high) = surfaces_under(perlin, seed, left_x_g, prefab.width);
+        if high - low > PREFAB_MAX_SURFACE_RANGE {
+            continue;
+        }
+        placements.push(PrefabPlacement { prefab: index, left_x_g, top_y_g: low - sink + prefab.height as i32 });
+    }
+    placements
+}
+
+pub fn buried_placements(seed: u64, chunk_x_g: i32, chunk_y_g: i32, perlin: &Perlin) -> Vec<PrefabPlacement> {
+    let mut placements = Vec::new();
+    let mut rng = chunk_rng(seed, chunk_x_g, chunk_y_g, BURIED_PREFAB_STREAM);
+    for (index, prefab) in prefabs().iter().enumerate() {
+        let Placement::Buried { min_depth } = prefab.placement else {
+            continue;
+        };
+        let (roll, x, y) = (rng.random::<f32>(), rng.random_range(0..chunk_size() as usize), rng.random_range(0..chunk_size() as usize));
+        if roll >= prefab.chance {
+            continue;
+        }
+        let (left_x_g, top_y_g) = (get_global_x_coordinate(chunk_x_g, x), get_global_y_coordinate(chunk_y_g, y));
+        if surfaces_under(perlin, seed, left_x_g, prefab.width).0 - top_y_g < min_depth {
+            continue;
+        }
+        placements.push(PrefabPlacement { prefab: index, left_x_g, top_y_g });
+    }
+    placements
+}
+
+pub fn place_prefabs(grid: &mut [u8], chunk_x_g: i32, chunk_y_g: i32, perlin: &Perlin, seed: u64) {
+    let reach_x = (prefabs().iter().map(|prefab| prefab.width).max().unwrap_or(0) as f32 / chunk_size()).ceil() as i32;
+    let reach_y = (prefabs().iter().map(|prefab| prefab.height).max().unwrap_or(0) as f32 / chunk_size()).ceil() as i32;
+    let mut placements = Vec::new();
+    for source_x_g in chunk_x_g - reach_x..=chunk_x_g {
+        placements.extend(surface_placements(seed, source_x_g, perlin));
+    }
+    for source_x_g in chunk_x_g - reach_x..=chunk_x_g {
+        for source_y_g in chunk_y_g..=chunk_y_g + reach_y {
+            placements.extend(buried_placements(seed, source_x_g, source_y_g, perlin));
+        }
+    }
+    let left_x_g = get_global_x_coordinate(chunk_x_g, 0);
+    let top_y_g = get_global_y_coordinate(chunk_y_g, 0);
+    for placement in placements {
+        let prefab = &prefabs()[placement.prefab];
+        for (index, cell) in prefab.cells.iter().enumerate() {
+            let Some(material) = cell else {
+                continue;
+            };
+            let x = placement.left_x_g + (index % prefab.width) as i32 - left_x_g;
+            let y = top_y_g - (placement.top_y_g - (index / prefab.width) as i32);
+            if (0..chunk_size() as i32).contains(&x) && (0..chunk_size() as i32).contains(&y) {
+                grid[y as usize * chunk_size() as usize + x as usize] = *material;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use noise::Perlin;
+
+    use crate::{color_map::DIRT1, constants::chunk_size, prefab::{place_prefabs, prefabs, surface_placements}, util::{get_global_x_coordinate, get_global_y_coordinate}};
+
+    #[test]
+    fn test_prefabs_stamp_across_chunk_seams() {
+        let perlin = Perlin::new(5);
+        let edge_x_g = get_global_x_coordinate(1, 0);
+        let (seed, placement) = (0..).find_map(|seed| {
+            surface_placements(seed, 0, &perlin).into_iter()
+                .find(|placement| placement.left_x_g + prefabs()[placement.prefab].width as i32 > edge_x_g + 1)
+                .map(|placement| (seed, placement))
+        }).unwrap();
+        let prefab = &prefabs()[placement.prefab];
+        let mut grids = HashMap::new();
+        let mut checked = 0;
+        for (index, cell) in prefab.cells.iter().enumerate() {
+            let (x_g, y_g) = (placement.left_x_g + (index % prefab.width) as i32, placement.top_y_g - (index / prefab.width) as i32);
+            let Some(material) = cell.filter(|_| x_g >= edge_x_g) else {
+                continue;
+            };
+            let chunk_y_g = (-4..=4).find(|chunk_y_g| (0..chunk_size() as i32).contains(&(get_global_y_coordinate(*chunk_y_g, 0) - y_g))).unwrap();
+            let grid = grids.entry(chunk_y_g).or_insert_with(|| {
+                let mut grid = vec![DIRT1; (chunk_size() * chunk_size()) as usize];
+                place_prefabs(&mut grid, 1, chunk_y_g, &perlin, seed);
+                grid
+            });
+            let local_y = get_global_y_coordinate(chunk_y_g, 0) - y_g;
+            assert_eq!(grid[(local_y * chunk_size() as i32 + x_g - edge_x_g) as usize], material);
+            checked += 1;
+        }
+        assert!(checked > 0);
+    }
+}
@@ -0,0 +1,202 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::sync::Mutex;
+use std::thread;
+
+use noise::Perlin;
+
+use crate::chunk_generator::generate_chunk;
+use crate::constants::{chunk_size, PREVIEW_FONT_SCALE, PREVIEW_GRID_OPACITY};
+use crate::material_registry::MaterialRegistry;
+use crate::seed::WorldSeed;
+
+const GLYPH_WIDTH: usize = 3;
+const GLYPH_HEIGHT: usize = 5;
+
+const GLYPHS: [(char, [u8; GLYPH_HEIGHT]); 40] = [
+    ('A', [0b010, 0b101, 0b111, 0b101, 0b101]), ('B', [0b110, 0b101, 0b110, 0b101, 0b110]),
+    ('C', [0b011, 0b100, 0b100, 0b100, 0b011]), ('D', [0b110, 0b101, 0b101, 0b101, 0b110]),
+    ('E', [0b111, 0b100, 0b110, 0b100, 0b111]), ('F', [0b111, 0b100, 0b110, 0b100, 0b100]),
+    ('G', [0b011, 0b100, 0b101, 0b101, 0b011]), ('H', [0b101, 0b101, 0b111, 0b101, 0b101]),
+    ('I', [0b111, 0b010, 0b010, 0b010, 0b111]), ('J', [0b001, 0b001, 0b001, 0b101, 0b010]),
+    ('K', [0b101, 0b101, 0b110, 0b101, 0b101]), ('L', [0b100, 0b100, 0b100, 0b100, 0b111]),
+    ('M', [0b101, 0b111, 0b111, 0b101, 0b101]), ('N', [0b110, 0b101, 0b101, 0b101, 0b101]),
+    ('O', [0b010, 0b101, 0b101, 0b101, 0b010]), ('P', [0b110, 0b101, 0b110, 0b100, 0b100]),
+    ('Q', [0b010, 0b101, 0b101, 0b110, 0b011]), ('R', [0b110, 0b101, 0b110, 0b101, 0b101]),
+    ('S', [0b011, 0b100, 0b010, 0b001, 0b110]), ('T', [0b111, 0b010, 0b010, 0b010, 0b010]),
+    ('U', [0b101, 0b101, 0b101, 0b101, 0b111]), ('V', [0b101, 0b101, 0b101, 0b101, 0b010]),
+    ('W', [0b101, 0b101, 0b111, 0b111, 0b101]), ('X', [0b101, 0b101, 0b010, 0b101, 0b101]),
+    ('Y', [0b101, 0b101, 0b010, 0b010, 0b010]), ('Z', [0b111, 0b001, 0b010, 0b100, 0b111]),
+    ('0', [0b111, 0b101, 0b101, 0b101, 0b111]), ('1', [0b010, 0b110, 0b010, 0b010, 0b111]),
+    ('2', [0b110, 0b001, 0b010, 0b100, 0b111]), ('3', [0b110, 0b001, 0b010, 0b001, 0b110]),
+    ('4', [0b101, 0b101, 0b111, 0b001, 0b001]), ('5', [0b111, 0b100, 0b110, 0b001, 0b110]),
+    ('6', [0b011, 0b100, 0b111, 0b101, 0b111]), ('7', [0b111, 0b001, 0b010, 0b010, 0b010]),
+    ('8', [0b111, 0b101, 0b111, 0b101, 0b111]), ('9', [0b111, 0b101, 0b111, 0b001, 0b110]),
+    ('_', [0b000, 0b000, 0b000, 0b000, 0b111]), ('.', [0b000, 0b000, 0b000, 0b000, 0b010]),
+    ('%', [0b101, 0b001, 0b010, 0b100, 0b101]), (' ', [0b000, 0b000, 0b000, 0b000, 0b000]),
+];
+
+pub struct PreviewOptions {
+    pub min_chunk: (i32, i32),
+    pub max_chunk: (i32, i32),
+    pub grid: bool,
+    pub legend: bool,
+}
+
+pub struct PreviewImage {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+impl PreviewImage {
+    fn new(width: usize, height: usize) -> PreviewImage {
+        PreviewImage { width, height, pixels: [0, 0, 0, 255].repeat(width * height) }
+    }
+
+    fn set(&mut self, x: usize, y: usize, color: [u8; 4]) {
+        let index = (y * self.width + x) * 4;
+        self.pixels[index..index + 4].copy_from_slice(&color);
+    }
+
+    fn blend(&mut self, x: usize, y: usize, color: [u8; 4], opacity: f32) {
+        let index = (y * self.width + x) * 4;
+        for (pixel, target) in self.pixels[index..index + 3].iter_mut().zip(color) {
+            *pixel = (*pixel as f32 + (target as f32 - *pixel as f32) * opacity).round() as u8;
+        }
+    }
+
+    fn fill(&mut self, x: usize, y: usize, width: usize, height: usize, color: [u8; 4]) {
+        for y in y..(y + height).min(self.height) {
+            for x in x..(x + width).min(self.width) {
+                self.set(x, y, color);
+            }
+        }
+    }
+
+    fn text(&mut self, x: usize, y: usize, text: &str, color: [u8; 4]) {
+        for (index, character) in text.to_uppercase().chars().enumerate() {
+            let Some((_, rows)) = GLYPHS.iter().find(|(glyph, _)| *glyph == character) else {
+                continue;
+            };
+            let left = x + index * (GLYPH_WIDTH + 1) * PREVIEW_FONT_SCALE;
+            for (row, bits) in rows.iter().enumerate() {
+                for column in 0..GLYPH_WIDTH {
+                    if bits & (1 << (GLYPH_WIDTH - 1 - column)) != 0 {
+                        self.fill(left + column * PREVIEW_FONT_SCALE, y + row * PREVIEW_FONT_SCALE, PREVIEW_FONT_SCALE, PREVIEW_FONT_SCALE, color);
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn generate_region(world_seed: &WorldSeed, options: &PreviewOptions) -> HashMap<(i32, i32), Vec<u8>> {
+    let perlin = Perlin::new(world_seed.perlin_seed());
+    let mut positions = Vec::new();
+    for chunk_y_g in options.min_chunk.1..=options.max_chunk.1 {
+        for chunk_x_g in options.min_chunk.0..=options.max_chunk.0 {
+            positions.push((chunk_x_g, chunk_y_g));
+        }
+    }
+    let queue = Mutex::new(positions);
+    let chunks = Mutex::new(HashMap::new());
+    let workers = thread::available_parallelism().map(|count| count.get()).unwrap_or(1);
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let Some((chunk_x_g, chunk_y_g)) = queue.lock().unwrap().pop() else {
+                    break;
+                };
+                let grid = generate_chunk(chunk_x_g, chunk_y_g, &perlin, world_seed.seed);
+                chunks.lock().unwrap().insert((chunk_x_g, chunk_y_g), grid);
+            });
+        }
+    });
+    chunks.into_inner().unwrap()
+}
+
+pub fn render_preview(chunks: &HashMap<(i32, i32), Vec<u8>>, options: &PreviewOptions, registry: &MaterialRegistry) -> PreviewImage {
+    let size = chunk_size() as usize;
+    let chunks_wide = (options.max_chunk.0 - options.min_chunk.0 + 1) as usize;
+    let chunks_high = (options.max_chunk.1 - options.min_chunk.1 + 1) as usize;
+    let (terrain_width, terrain_height) = (chunks_wide * size, chunks_high * size);
+    let mut counts = vec![0usize; registry.materials.len()];
+    for grid in chunks.values() {
+        for pixel in grid.iter() {
+            counts[*pixel as usize] += 1;
+        }
+    }
+    let mut present: Vec<usize> = (0..counts.len()).filter(|material| counts[*material] > 0).collect();
+    present.sort_by_key(|material| std::cmp::Reverse(counts[*material]));
+    let total = counts.iter().sum::<usize>().max(1);
+    let lines: Vec<(usize, String)> = present.iter().map(|material| (*material, format!("{} {:.1}%", registry.materials[*material].name, counts[*material] as f32 * 100. / total as f32))).collect();
+    let line_height = (GLYPH_HEIGHT + 2) * PREVIEW_FONT_SCALE;
+    let swatch = GLYPH_HEIGHT * PREVIEW_FONT_SCALE;
+    let (legend_width, legend_height) = if options.legend {
+        let longest = lines.iter().map(|(_, line)| line.len()).max().unwrap_or(0);
+        (swatch + (2 + longest * (GLYPH_WIDTH + 1)) * PREVIEW_FONT_SCALE + line_height, (lines.len() + 1) * line_height)
+    } else {
+        (0, 0)
+    };
+    let palette: Vec<[u8; 4]> = registry.materials.iter().map(|material| material.color).collect();
+    let mut image = PreviewImage::new(terrain_width + legend_width, terrain_height.max(legend_height));
+    for ((chunk_x_g, chunk_y_g), grid) in chunks.iter() {
+        let left = (chunk_x_g - options.min_chunk.0) as usize * size;
+        let top = (options.max_chunk.1 - chunk_y_g) as usize * size;
+        for (index, pixel) in grid.iter().enumerate() {
+            image.set(left + index % size, top + index / size, palette[*pixel as usize]);
+        }
+    }
+    if options.grid {
+        for y in 0..terrain_height {
+            for x in 0..terrain_width {
+                if x % size == 0 || y % size == 0 {
+                    image.blend(x, y, [255, 255, 255, 255], PREVIEW_GRID_OPACITY);
+                }
+            }
+        }
+    }
+    if options.legend {
+        for (row, (material, line)) in lines.iter().enumerate() {
+            let y = line_height / 2 + row * line_height;
+            image.fill(terrain_width + line_height / 2, y, swatch, swatch, palette[*material]);
+            image.text(terrain_width + line_height / 2 + swatch + 2 * PREVIEW_FONT_SCALE, y, line, [255, 255, 255, 255]);
+        }
+    }
+    image
+}
+
+pub fn write_png(image: &PreviewImage, path: &str) -> io::Result<()> {
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), image.width as u32, image.height as u32);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_source_srgb(png::SrgbRenderingIntent::Perceptual);
+    let mut writer = encoder.write_header().map_err(io::Error::other)?;
+    writer.write_image_data(&image.pixels).map_err(io::Error::other)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{color_map::{DIRT1, SKY}, constants::chunk_size, material_registry::MaterialRegistry, preview::{render_preview, PreviewOptions}};
+
+    #[test]
+    fn test_render_preview_layout() {
+        let size = chunk_size() as usize;
+        let mut chunks = HashMap::new();
+        chunks.insert((0, 1), vec![SKY; size * size]);
+        chunks.insert((0, 0), vec![DIRT1; size * size]);
+        let options = PreviewOptions { min_chunk: (0, 0), max_chunk: (0, 1), grid: true, legend: true };
+        let registry = MaterialRegistry::default();
+        let image = render_preview(&chunks, &options, &registry);
+        assert!(image.width > size);
+        assert_eq!(image.height, 2 * size);
+        let pixel = |x: usize, y: usize| image.pixels[(y * image.width + x) * 4..(y * image.width + x) * 4 + 4].to_vec();
+        assert_eq!(pixel(1, 1), registry.get(SKY).color.to_vec());
+        assert_eq!(pixel(1, size + 1), registry.get(DIRT1).color.to_vec());
+        assert_ne!(pixel(0, size + 1), registry.get(DIRT1).color.to_vec());
+    }
+}
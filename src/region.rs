@@ -0,0 +1,149 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::PathBuf;
+
+use bevy::log::warn;
+use bevy::prelude::{Query, Transform};
+
+use crate::components::{ChunkLoader, ChunkMap};
+use crate::constants::{MAX_REGION_WRITES_PER_EVICTION, REGION_DIRECTORY, REGION_SIZE};
+use crate::save::{read_chunk_record, write_chunk_record, ChunkRecord};
+use crate::util::{get_chunk_x_g, get_chunk_y_g};
+
+const REGION_MAGIC: &[u8; 4] = b"TWBR";
+
+pub fn get_region(chunk_x_g: i32, chunk_y_g: i32) -> (i32, i32) {
+    (chunk_x_g.div_euclid(REGION_SIZE), chunk_y_g.div_euclid(REGION_SIZE))
+}
+
+fn region_path(region: (i32, i32)) -> PathBuf {
+    PathBuf::from(REGION_DIRECTORY).join(format!("r.{}.{}.bin", region.0, region.1))
+}
+
+fn read_region(region: (i32, i32)) -> io::Result<HashMap<(i32, i32), Vec<u8>>> {
+    let path = region_path(region);
+    let mut chunks = HashMap::new();
+    if !path.exists() {
+        return Ok(chunks);
+    }
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut magic = [0; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != REGION_MAGIC {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "not a region file"));
+    }
+    let mut count = [0; 4];
+    reader.read_exact(&mut count)?;
+    for _ in 0..u32::from_le_bytes(count) {
+        let record = read_chunk_record(&mut reader)?;
+        chunks.insert((record.chunk_x_g, record.chunk_y_g), record.data);
+    }
+    Ok(chunks)
+}
+
+fn write_region(region: (i32, i32), chunks: HashMap<(i32, i32), Vec<u8>>) -> io::Result<()> {
+    fs::create_dir_all(REGION_DIRECTORY)?;
+    let path = region_path(region);
+    let partial = path.with_extension("partial");
+    let mut writer = BufWriter::new(File::create(&partial)?);
+    writer.write_all(REGION_MAGIC)?;
+    writer.write_all(&(chunks.len() as u32).to_le_bytes())?;
+    for ((chunk_x_g, chunk_y_g), data) in chunks {
+        write_chunk_record(&mut writer, &ChunkRecord { chunk_x_g, chunk_y_g, data })?;
+    }
+    writer.flush()?;
+    drop(writer);
+    fs::rename(partial, path)
+}
+
+pub fn store_chunks_in_region(region: (i32, i32), evicted: Vec<((i32, i32), Vec<u8>)>) -> io::Result<()> {
+    let mut chunks = read_region(region)?;
+    chunks.extend(evicted);
+    write_region(region, chunks)
+}
+
+pub fn load_chunk_from_region(chunk_x_g: i32, chunk_y_g: i32) -> Option<Vec<u8>> {
+    match read_region(get_region(chunk_x_g, chunk_y_g)) {
+        Ok(mut chunks) => chunks.remove(&(chunk_x_g, chunk_y_g)),
+        Err(err) => {
+            warn!("Could not read region for chunk {}, {}: {}", chunk_x_g, chunk_y_g, err);
+            None
+        }
+    }
+}
+
+pub fn clear_regions() {
+    if let Err(err) = fs::remove_dir_all(REGION_DIRECTORY) {
+        if err.kind() != io::ErrorKind::NotFound {
+            warn!("Could not clear {}: {}", REGION_DIRECTORY, err);
+        }
+    }
+}
+
+// Chunks around ones that are still simulating, which must stay loaded and writable
+fn pinned_chunks(chunk_map: &ChunkMap) -> HashSet<(i32, i32)> {
+    let mut pinned_chunks = HashSet::new();
+    for (chunk_x_g, chunk_y_g) in chunk_map.active.keys() {
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                pinned_chunks.insert((chunk_x_g + dx, chunk_y_g + dy));
+            }
+        }
+    }
+    pinned_chunks
+}
+
+pub fn evict_distant_chunks(
+    loader_query: Query<(&Transform, &ChunkLoader)>,
+    mut chunk_map_query: Query<&mut ChunkMap>,
+) {
+    let loaders: Vec<(i32, i32, i32)> = loader_query.iter()
+        .map(|(transform, loader)| (get_chunk_x_g(transform.translation.x as i32), get_chunk_y_g(transform.translation.y as i32), loader.radius))
+        .collect();
+    if loaders.is_empty() {
+        return;
+    }
+    let mut chunk_map = chunk_map_query.get_single_mut().unwrap();
+    let pinned_chunks = pinned_chunks(&chunk_map);
+    let distant_chunks: Vec<(i32, i32)> = chunk_map.map.keys()
+        .filter(|chunk| !pinned_chunks.contains(*chunk))
+        .filter(|(chunk_x_g, chunk_y_g)| {
+            loaders.iter().all(|(loader_x, loader_y, radius)| (chunk_x_g - loader_x).abs() > *radius || (chunk_y_g - loader_y).abs() > *radius)
+        })
+        .cloned()
+        .collect();
+    let mut edited_by_region: HashMap<(i32, i32), Vec<(i32, i32)>> = HashMap::new();
+    for chunk_pos in distant_chunks {
+        if chunk_map.edited.contains(&chunk_pos) {
+            edited_by_region.entry(get_region(chunk_pos.0, chunk_pos.1)).or_default().push(chunk_pos);
+        } else {
+            chunk_map.map.remove(&chunk_pos);
+            chunk_map.damage.remove(&chunk_pos);
+        }
+    }
+    // Each region is rewritten whole, so only a few are written per sweep and the rest wait for the next one
+    for (region, chunk_positions) in edited_by_region.into_iter().take(MAX_REGION_WRITES_PER_EVICTION) {
+        let evicted: Vec<((i32, i32), Vec<u8>)> = chunk_positions.iter().map(|chunk_pos| (*chunk_pos, chunk_map.map[chunk_pos].to_dense())).collect();
+        if let Err(err) = store_chunks_in_region(region, evicted) {
+            warn!("Could not write region {}, {}: {}", region.0, region.1, err);
+            continue;
+        }
+        for chunk_pos in chunk_positions {
+            chunk_map.map.remove(&chunk_pos);
+            chunk_map.edited.remove(&chunk_pos);
+            chunk_map.damage.remove(&chunk_pos);
+        }
+    }
+}
+
+// Edits leave chunks dense, so once nothing is simulating near them they are packed back down
+pub fn compact_settled_chunks(mut chunk_map_query: Query<&mut ChunkMap>) {
+    let mut chunk_map = chunk_map_query.get_single_mut().unwrap();
+    let pinned_chunks = pinned_chunks(&chunk_map);
+    for (chunk_pos, chunk) in chunk_map.map.iter_mut() {
+        if !pinned_chunks.contains(chunk_pos) {
+            chunk.compact();
+        }
+    }
+}
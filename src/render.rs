@@ -1,18 +1,80 @@
-use bevy::{asset::{Assets, Handle}, ecs::event::EventWriter, math::Vec3, prelude::{Image, Query, ResMut, Transform, With, Without}};
+use bevy::{app::{App, Plugin}, asset::{AssetId, Assets, Handle}, ecs::event::EventWriter, math::Vec3, prelude::{Image, IntoSystemConfigs, Query, Res, ResMut, Resource, Transform, With, Without}, render::{render_asset::RenderAssets, render_resource::{Extent3d, ImageCopyTexture, ImageDataLayout, Origin3d, TextureAspect}, renderer::RenderQueue, texture::GpuImage, ExtractSchedule, MainWorld, Render, RenderApp, RenderSet}};
 
-use crate::{chunk_generator::NewChunkEvent, components::{CameraTag, ChunkMap, PlayerTag, TerrainImageTag}, compute_shader::CurrentPlayerPosition, constants::CHUNK_SIZE, materials::GridMaterial, util::{get_chunk_x_g, get_chunk_y_g}};
+use crate::{chunk::DirtyRect, chunk_generator::NewChunkEvent, components::{CameraTag, ChunkMap, PlayerTag, TerrainImageTag}, compute_shader::CurrentPlayerPosition, constants::chunk_size, materials::GridMaterial, util::{get_chunk_x_g, get_chunk_y_g}};
+
+pub struct TextureWrite {
+    image: AssetId<Image>,
+    rect: DirtyRect,
+    data: Vec<u8>,
+}
+
+// Chunk pixels copied straight into sub-regions of the GPU textures. Going through
+// Assets::get_mut would re-upload the whole texture for every changed pixel.
+#[derive(Resource, Default)]
+pub struct TextureWrites {
+    writes: Vec<TextureWrite>,
+}
+
+impl TextureWrites {
+    pub fn push(&mut self, image: &Handle<Image>, rect: &DirtyRect, data: Vec<u8>) {
+        self.writes.push(TextureWrite { image: image.id(), rect: *rect, data });
+    }
+}
+
+pub struct TextureWritePlugin;
+
+impl Plugin for TextureWritePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TextureWrites>();
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app.init_resource::<TextureWrites>();
+        render_app.add_systems(ExtractSchedule, extract_texture_writes);
+        render_app.add_systems(Render, write_textures.in_set(RenderSet::PrepareResources));
+    }
+}
+
+fn extract_texture_writes(mut main_world: ResMut<MainWorld>, mut texture_writes: ResMut<TextureWrites>) {
+    let mut extracted = std::mem::take(&mut main_world.resource_mut::<TextureWrites>().writes);
+    texture_writes.writes.append(&mut extracted);
+}
+
+fn write_textures(mut texture_writes: ResMut<TextureWrites>, gpu_images: Res<RenderAssets<GpuImage>>, render_queue: Res<RenderQueue>) {
+    // Writes for textures that have not reached the GPU yet are kept, in order, for the next frame
+    let ready = texture_writes.writes.iter().position(|write| gpu_images.get(write.image).is_none()).unwrap_or(texture_writes.writes.len());
+    for write in texture_writes.writes.drain(..ready) {
+        if let Some(gpu_image) = gpu_images.get(write.image) {
+            write_texture(&render_queue, gpu_image, &write);
+        }
+    }
+}
+
+fn write_texture(render_queue: &RenderQueue, gpu_image: &GpuImage, write: &TextureWrite) {
+    let width = (write.rect.max_x - write.rect.min_x + 1) as u32;
+    let height = (write.rect.max_y - write.rect.min_y + 1) as u32;
+    render_queue.write_texture(
+        ImageCopyTexture {
+            texture: &gpu_image.texture,
+            mip_level: 0,
+            origin: Origin3d { x: write.rect.min_x as u32, y: write.rect.min_y as u32, z: 0 },
+            aspect: TextureAspect::All,
+        },
+        &write.data,
+        ImageDataLayout { offset: 0, bytes_per_row: Some(width), rows_per_image: None },
+        Extent3d { width, height, depth_or_array_layers: 1 },
+    );
+}
 
 pub fn render(
     mut materials: ResMut<Assets<GridMaterial>>,
     mut terrain_material_handle: Query<(&Handle<GridMaterial>, &mut Transform), (With<TerrainImageTag>, Without<PlayerTag>)>,
-    mut images: ResMut<bevy::asset::Assets<Image>>,
+    mut texture_writes: ResMut<TextureWrites>,
     player_query: Query<&Transform, (With<PlayerTag>, Without<TerrainImageTag>, Without<CameraTag>)>,
     mut chunk_map_query: Query<&mut ChunkMap>,
     mut camera_query: Query<&mut Transform, (With<CameraTag>, Without<PlayerTag>, Without<TerrainImageTag>)>,
     mut chunk_event_writer: EventWriter<NewChunkEvent>,
     mut current_player_position: ResMut<CurrentPlayerPosition>,
 ) {
-    let chunk_map = &mut chunk_map_query.get_single_mut().unwrap().map;
+    let mut chunk_map = chunk_map_query.get_single_mut().unwrap();
     let player_pos = player_query
         .get_single()
         .map(|player| player.translation)
@@ -24,14 +86,22 @@ pub fn render(
     current_player_position.position[1] = player_pos.y;
     for (material_handle, mut rendered_box_transform) in terrain_material_handle.iter_mut() {
         let material_handle = materials.get_mut(material_handle).unwrap();
-        let grid_image = images.get_mut(&material_handle.color_map_handle).unwrap();
         let global_chunk_pos = &mut material_handle.global_chunk_pos;
         let player_pos_on_texture = &mut material_handle.player_pos;
         player_pos_on_texture.x = player_pos.x;
         player_pos_on_texture.y = player_pos.y;
-        if let Some(chunk) = chunk_map.get(&(chunk_x_g + material_handle.on_screen_chunk_position[0] as i32, chunk_y_g + material_handle.on_screen_chunk_position[1] as i32)) {
-            grid_image.data = chunk.clone();
+        let target_chunk = (chunk_x_g + material_handle.on_screen_chunk_position[0] as i32, chunk_y_g + material_handle.on_screen_chunk_position[1] as i32);
+        if let Some(chunk) = chunk_map.map.get(&target_chunk) {
+            if material_handle.displayed_chunk != Some(target_chunk) {
+                texture_writes.push(&material_handle.color_map_handle, &DirtyRect::full(), chunk.to_dense());
+                texture_writes.push(&material_handle.damage_map_handle, &DirtyRect::full(), chunk_map.damage_rect_data(target_chunk, &DirtyRect::full()));
+                material_handle.displayed_chunk = Some(target_chunk);
+            } else if let Some(dirty_rect) = chunk_map.dirty.get(&target_chunk) {
+                texture_writes.push(&material_handle.color_map_handle, dirty_rect, chunk.rect_data(dirty_rect));
+                texture_writes.push(&material_handle.damage_map_handle, dirty_rect, chunk_map.damage_rect_data(target_chunk, dirty_rect));
+            }
         } else {
+            material_handle.displayed_chunk = None;
             chunk_event_writer.send(NewChunkEvent {
                 chunk_x_g: chunk_x_g + material_handle.on_screen_chunk_position[0] as i32,
                 chunk_y_g: chunk_y_g + material_handle.on_screen_chunk_position[1] as i32,
@@ -39,7 +109,8 @@ pub fn render(
         }
         global_chunk_pos.x = chunk_x_g as f32 + material_handle.on_screen_chunk_position[0] as f32;
         global_chunk_pos.y = chunk_y_g as f32 + material_handle.on_screen_chunk_position[1] as f32;
-        rendered_box_transform.translation.x = (chunk_x_g as f32 + material_handle.on_screen_chunk_position[0] as f32) * CHUNK_SIZE;
-        rendered_box_transform.translation.y = (chunk_y_g as f32 + material_handle.on_screen_chunk_position[1] as f32) * CHUNK_SIZE;
+        rendered_box_transform.translation.x = (chunk_x_g as f32 + material_handle.on_screen_chunk_position[0] as f32) * chunk_size();
+        rendered_box_transform.translation.y = (chunk_y_g as f32 + material_handle.on_screen_chunk_position[1] as f32) * chunk_size();
     }
+    chunk_map.dirty.clear();
 }
\ No newline at end of file
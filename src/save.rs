@@ -0,0 +1,237 @@
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use bevy::app::AppExit;
+use bevy::ecs::event::EventReader;
+use bevy::input::ButtonInput;
+use bevy::log::{info, warn};
+use bevy::prelude::{Commands, KeyCode, Query, Res, Resource, Transform, With, Without};
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+
+use crate::components::{ChunkMap, ContentList, Count, PlayerTag, Velocity};
+use crate::constants::{chunk_size, WORLD_FORMAT_VERSION, WORLD_SAVE_PATH};
+use crate::seed::WorldSeed;
+use crate::tools::ShovelTag;
+
+const WORLD_MAGIC: &[u8; 4] = b"TWBW";
+
+pub struct ChunkRecord {
+    pub chunk_x_g: i32,
+    pub chunk_y_g: i32,
+    pub data: Vec<u8>,
+}
+
+pub struct PlayerRecord {
+    pub x: f32,
+    pub y: f32,
+    pub vx: f32,
+    pub vy: f32,
+}
+
+pub struct WorldSave {
+    pub seed: u64,
+    pub chunk_size: u32,
+    pub player: PlayerRecord,
+    pub money: f32,
+    pub shovel_contents: Vec<u8>,
+    pub chunks: Vec<ChunkRecord>,
+}
+
+#[derive(Resource)]
+pub struct PendingWorldRestore {
+    pub player: PlayerRecord,
+    pub money: f32,
+    pub shovel_contents: Vec<u8>,
+}
+
+pub fn write_world_save(path: &Path, save: &WorldSave) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // Write beside the old save and swap it in, so a failed write never clobbers it
+    let partial = path.with_extension("partial");
+    let mut writer = BufWriter::new(File::create(&partial)?);
+    writer.write_all(WORLD_MAGIC)?;
+    writer.write_all(&WORLD_FORMAT_VERSION.to_le_bytes())?;
+    writer.write_all(&save.seed.to_le_bytes())?;
+    writer.write_all(&save.chunk_size.to_le_bytes())?;
+    for value in [save.player.x, save.player.y, save.player.vx, save.player.vy, save.money] {
+        writer.write_all(&value.to_le_bytes())?;
+    }
+    write_compressed_block(&mut writer, &save.shovel_contents)?;
+    writer.write_all(&(save.chunks.len() as u32).to_le_bytes())?;
+    for chunk in save.chunks.iter() {
+        write_chunk_record(&mut writer, chunk)?;
+    }
+    writer.flush()?;
+    drop(writer);
+    fs::rename(partial, path)
+}
+
+pub fn read_world_save(path: &Path) -> io::Result<WorldSave> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut magic = [0; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != WORLD_MAGIC {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "not a world save file"));
+    }
+    let version = read_u32(&mut reader)?;
+    if version != WORLD_FORMAT_VERSION {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("unsupported world format version {}, expected {}", version, WORLD_FORMAT_VERSION)));
+    }
+    let seed = read_u64(&mut reader)?;
+    let saved_chunk_size = read_u32(&mut reader)?;
+    if saved_chunk_size != chunk_size() as u32 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("world was saved with chunk size {}, current chunk size is {}", saved_chunk_size, chunk_size())));
+    }
+    let player = PlayerRecord {
+        x: read_f32(&mut reader)?,
+        y: read_f32(&mut reader)?,
+        vx: read_f32(&mut reader)?,
+        vy: read_f32(&mut reader)?,
+    };
+    let money = read_f32(&mut reader)?;
+    let shovel_contents = read_compressed_block(&mut reader)?;
+    let chunk_count = read_u32(&mut reader)?;
+    let mut chunks = Vec::with_capacity(chunk_count as usize);
+    for _ in 0..chunk_count {
+        chunks.push(read_chunk_record(&mut reader)?);
+    }
+    Ok(WorldSave { seed, chunk_size: saved_chunk_size, player, money, shovel_contents, chunks })
+}
+
+pub fn write_chunk_record(writer: &mut impl Write, chunk: &ChunkRecord) -> io::Result<()> {
+    writer.write_all(&chunk.chunk_x_g.to_le_bytes())?;
+    writer.write_all(&chunk.chunk_y_g.to_le_bytes())?;
+    write_compressed_block(writer, &chunk.data)
+}
+
+pub fn read_chunk_record(reader: &mut impl Read) -> io::Result<ChunkRecord> {
+    let chunk_x_g = read_i32(reader)?;
+    let chunk_y_g = read_i32(reader)?;
+    let data = read_compressed_block(reader)?;
+    if data.len() != (chunk_size() * chunk_size()) as usize {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("chunk {}, {} has {} pixels", chunk_x_g, chunk_y_g, data.len())));
+    }
+    Ok(ChunkRecord { chunk_x_g, chunk_y_g, data })
+}
+
+fn write_compressed_block(writer: &mut impl Write, data: &[u8]) -> io::Result<()> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data)?;
+    let compressed = encoder.finish()?;
+    writer.write_all(&(compressed.len() as u32).to_le_bytes())?;
+    writer.write_all(&compressed)
+}
+
+fn read_compressed_block(reader: &mut impl Read) -> io::Result<Vec<u8>> {
+    let compressed_len = read_u32(reader)?;
+    let mut compressed = vec![0; compressed_len as usize];
+    reader.read_exact(&mut compressed)?;
+    let mut data = Vec::new();
+    ZlibDecoder::new(&compressed[..]).read_to_end(&mut data)?;
+    Ok(data)
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_i32(reader: &mut impl Read) -> io::Result<i32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(i32::from_le_bytes(bytes))
+}
+
+fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_f32(reader: &mut impl Read) -> io::Result<f32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(f32::from_le_bytes(bytes))
+}
+
+// Returns Ok(None) only when there is no save yet, so a save that fails to read is never mistaken for a new world
+pub fn load_world_save() -> io::Result<Option<WorldSave>> {
+    let path = Path::new(WORLD_SAVE_PATH);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let save = read_world_save(path)?;
+    info!("Loaded world from {} with {} edited chunks", WORLD_SAVE_PATH, save.chunks.len());
+    Ok(Some(save))
+}
+
+pub fn restore_world_save(
+    mut commands: Commands,
+    pending_restore: Option<Res<PendingWorldRestore>>,
+    mut player_query: Query<(&mut Transform, &mut Velocity), With<PlayerTag>>,
+    mut shovel_contents_query: Query<&mut ContentList, (With<ShovelTag>, Without<PlayerTag>)>,
+    mut money_count_query: Query<&mut Count>,
+) {
+    let Some(pending_restore) = pending_restore else {
+        return;
+    };
+    if let Ok((mut transform, mut velocity)) = player_query.get_single_mut() {
+        transform.translation.x = pending_restore.player.x;
+        transform.translation.y = pending_restore.player.y;
+        velocity.vx = pending_restore.player.vx;
+        velocity.vy = pending_restore.player.vy;
+    }
+    if let Ok(mut shovel_contents) = shovel_contents_query.get_single_mut() {
+        shovel_contents.contents = pending_restore.shovel_contents.clone();
+    }
+    if let Ok(mut money_count) = money_count_query.get_single_mut() {
+        money_count.count = pending_restore.money;
+    }
+    commands.remove_resource::<PendingWorldRestore>();
+}
+
+pub fn save_world(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut exit_events: EventReader<AppExit>,
+    chunk_map_query: Query<&ChunkMap>,
+    world_seed: Res<WorldSeed>,
+    player_query: Query<(&Transform, &Velocity), With<PlayerTag>>,
+    shovel_contents_query: Query<&ContentList, With<ShovelTag>>,
+    money_count_query: Query<&Count>,
+) {
+    let exiting = exit_events.read().count() > 0;
+    if !exiting && !keys.just_pressed(KeyCode::F5) {
+        return;
+    }
+    let chunk_map = chunk_map_query.get_single().unwrap();
+    let player = player_query
+        .get_single()
+        .map(|(transform, velocity)| PlayerRecord { x: transform.translation.x, y: transform.translation.y, vx: velocity.vx, vy: velocity.vy })
+        .unwrap_or(PlayerRecord { x: 0., y: 0., vx: 0., vy: 0. });
+    let save = WorldSave {
+        seed: world_seed.seed,
+        chunk_size: chunk_size() as u32,
+        player,
+        money: money_count_query.get_single().map(|count| count.count).unwrap_or(0.),
+        shovel_contents: shovel_contents_query.get_single().map(|contents| contents.contents.clone()).unwrap_or_default(),
+        chunks: edited_chunk_records(chunk_map),
+    };
+    match write_world_save(Path::new(WORLD_SAVE_PATH), &save) {
+        Ok(()) => info!("Saved {} edited chunks to {}", save.chunks.len(), WORLD_SAVE_PATH),
+        Err(err) => warn!("Could not save world to {}: {}", WORLD_SAVE_PATH, err),
+    }
+}
+
+fn edited_chunk_records(chunk_map: &ChunkMap) -> Vec<ChunkRecord> {
+    chunk_map.edited.iter()
+        .filter_map(|&(chunk_x_g, chunk_y_g)| {
+            chunk_map.map.get(&(chunk_x_g, chunk_y_g)).map(|chunk| ChunkRecord { chunk_x_g, chunk_y_g, data: chunk.to_dense() })
+        })
+        .collect()
+}
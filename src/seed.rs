@@ -0,0 +1,66 @@
+use bevy::prelude::Resource;
+use rand::{rngs::SmallRng, SeedableRng};
+
+use crate::util::arg_value;
+
+pub const LAYER_STREAM: u64 = 1;
+pub const BIOME_STREAM: u64 = 2;
+pub const GRAVEL_STREAM: u64 = 3;
+pub const COPPER_STREAM: u64 = 4;
+pub const SILVER_STREAM: u64 = 5;
+pub const SURFACE_PREFAB_STREAM: u64 = 6;
+pub const BURIED_PREFAB_STREAM: u64 = 7;
+pub const TREE_STREAM: u64 = 8;
+pub const PLANT_STREAM: u64 = 9;
+pub const MARKET_STREAM: u64 = 10;
+pub const LAKE_STREAM: u64 = 11;
+pub const WATER_POCKET_STREAM: u64 = 12;
+
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct WorldSeed {
+    pub seed: u64,
+}
+
+impl WorldSeed {
+    pub fn from_seed_string(seed_string: &str) -> WorldSeed {
+        if let Ok(seed) = seed_string.trim().parse::<u64>() {
+            return WorldSeed { seed };
+        }
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for byte in seed_string.trim().bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+        WorldSeed { seed: hash }
+    }
+
+    pub fn from_args() -> WorldSeed {
+        match arg_value("--seed") {
+            Some(seed_string) => WorldSeed::from_seed_string(&seed_string),
+            None => WorldSeed { seed: rand::random() },
+        }
+    }
+
+    pub fn perlin_seed(&self) -> u32 {
+        (self.seed ^ (self.seed >> 32)) as u32
+    }
+}
+
+fn mix(mut value: u64) -> u64 {
+    value = value.wrapping_add(0x9e3779b97f4a7c15);
+    value = (value ^ (value >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94d049bb133111eb);
+    value ^ (value >> 31)
+}
+
+pub fn coordinate_hash(seed: u64, x: i32, y: i32, stream: u64) -> u64 {
+    mix(mix(mix(seed ^ mix(stream)) ^ x as u32 as u64) ^ y as u32 as u64)
+}
+
+pub fn chunk_rng(seed: u64, chunk_x_g: i32, chunk_y_g: i32, stream: u64) -> SmallRng {
+    SmallRng::seed_from_u64(coordinate_hash(seed, chunk_x_g, chunk_y_g, stream))
+}
+
+pub fn pixel_rng(seed: u64, x_g: i32, y_g: i32, stream: u64) -> SmallRng {
+    SmallRng::seed_from_u64(coordinate_hash(seed, x_g, y_g, stream.wrapping_add(0x1000)))
+}
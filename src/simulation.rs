@@ -0,0 +1,426 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+use bevy::tasks::{ComputeTaskPool, TaskPool};
+
+use crate::chunk::{Chunk, DirtyRect};
+use crate::color_map::SKY;
+use crate::components::ChunkMap;
+use crate::constants::{chunk_size, LIQUID_FLOW_DISTANCE, LIQUID_LEVEL_MAX_BODY};
+use crate::history::EditTransaction;
+use crate::market::Market;
+use crate::material_registry::{MaterialRegistry, Slide};
+use crate::terrain_world::{global_position, locate};
+
+const CHECKERBOARD: [(i32, i32); 4] = [(0, 0), (1, 0), (0, 1), (1, 1)];
+
+trait PixelWorld {
+    fn get(&self, x_g: i32, y_g: i32) -> Option<u8>;
+    fn writable(&self, x_g: i32, y_g: i32) -> bool;
+    fn write(&mut self, x_g: i32, y_g: i32, pixel: u8);
+    fn wake(&mut self, x_g: i32, y_g: i32);
+
+    fn set(&mut self, x_g: i32, y_g: i32, pixel: u8) -> bool {
+        if !self.writable(x_g, y_g) {
+            return false;
+        }
+        self.write(x_g, y_g, pixel);
+        true
+    }
+
+    fn swap(&mut self, a: (i32, i32), b: (i32, i32)) -> bool {
+        if !self.writable(a.0, a.1) || !self.writable(b.0, b.1) {
+            return false;
+        }
+        let (pixel_a, pixel_b) = (self.get(a.0, a.1).unwrap(), self.get(b.0, b.1).unwrap());
+        self.write(a.0, a.1, pixel_b);
+        self.write(b.0, b.1, pixel_a);
+        true
+    }
+}
+
+// One chunk being updated on a worker. It can read its neighbors, which belong to
+// the other checkerboard colors and so are not written during this pass, but it
+// only writes its own chunk. Moves that would leave the chunk are deferred.
+struct ChunkNeighborhood<'a> {
+    chunk_pos: (i32, i32),
+    chunk: &'a mut Chunk,
+    chunks: &'a HashMap<(i32, i32), Chunk>,
+    requested: &'a Mutex<HashSet<(i32, i32)>>,
+    edits: Vec<((i32, i32), u8, u8)>,
+    wakes: Vec<(i32, i32)>,
+}
+
+impl PixelWorld for ChunkNeighborhood<'_> {
+    fn get(&self, x_g: i32, y_g: i32) -> Option<u8> {
+        let (chunk_pos, local_index) = locate(x_g, y_g);
+        if chunk_pos == self.chunk_pos {
+            return Some(self.chunk.get(local_index));
+        }
+        let pixel = self.chunks.get(&chunk_pos).map(|chunk| chunk.get(local_index));
+        if pixel.is_none() {
+            self.requested.lock().unwrap().insert(chunk_pos);
+        }
+        pixel
+    }
+
+    fn writable(&self, x_g: i32, y_g: i32) -> bool {
+        locate(x_g, y_g).0 == self.chunk_pos
+    }
+
+    fn write(&mut self, x_g: i32, y_g: i32, pixel: u8) {
+        let local_index = locate(x_g, y_g).1;
+        self.edits.push(((x_g, y_g), self.chunk.get(local_index), pixel));
+        self.chunk.set(local_index, pixel);
+    }
+
+    fn wake(&mut self, x_g: i32, y_g: i32) {
+        self.wakes.push((x_g, y_g));
+    }
+}
+
+struct SerialWorld<'a> {
+    chunk_map: &'a mut ChunkMap,
+    edits: Vec<((i32, i32), u8, u8)>,
+}
+
+impl PixelWorld for SerialWorld<'_> {
+    fn get(&self, x_g: i32, y_g: i32) -> Option<u8> {
+        self.chunk_map.get(x_g, y_g)
+    }
+
+    fn writable(&self, x_g: i32, y_g: i32) -> bool {
+        self.chunk_map.map.contains_key(&locate(x_g, y_g).0)
+    }
+
+    fn write(&mut self, x_g: i32, y_g: i32, pixel: u8) {
+        let old = self.chunk_map.get(x_g, y_g).unwrap();
+        self.chunk_map.set(x_g, y_g, pixel).unwrap();
+        self.edits.push(((x_g, y_g), old, pixel));
+    }
+
+    fn wake(&mut self, x_g: i32, y_g: i32) {
+        self.chunk_map.wake(x_g, y_g);
+    }
+}
+
+#[derive(Default)]
+struct StepState {
+    left_first: bool,
+    sold: HashMap<u8, u32>,
+    sales: Vec<((i32, i32), u8)>,
+    moves: Vec<((i32, i32), (i32, i32))>,
+    leveled: HashSet<(i32, i32)>,
+    moved: HashSet<(i32, i32)>,
+    deferred: Vec<(i32, i32)>,
+}
+
+fn slide_target(world: &impl PixelWorld, slide: Slide, x: i32, y: i32, step_y: i32, left_first: bool, open: impl Fn(u8) -> bool) -> Option<(i32, i32)> {
+    let directions = if left_first { [-1, 1] } else { [1, -1] };
+    for direction in directions {
+        for step in 1..=slide.run {
+            let side_x = x + direction * step;
+            if !world.get(side_x, y).is_some_and(&open) {
+                break;
+            }
+            if (1..=slide.drop).all(|depth| world.get(side_x, y + step_y * depth).is_some_and(&open)) {
+                return Some((side_x, y + step_y));
+            }
+        }
+    }
+    None
+}
+
+fn move_pixel(world: &mut impl PixelWorld, state: &mut StepState, from: (i32, i32), to: (i32, i32)) {
+    if world.swap(from, to) {
+        state.moved.insert(to);
+        state.moves.push((from, to));
+    } else {
+        state.deferred.push(from);
+    }
+}
+
+fn level_basin(world: &mut impl PixelWorld, state: &mut StepState, registry: &MaterialRegistry, x: i32, y: i32) {
+    let Some(liquid) = world.get(x, y) else {
+        return;
+    };
+    let mut body = vec![(x, y)];
+    state.leveled.insert((x, y));
+    let mut top: Option<(i32, i32)> = None;
+    let mut gap: Option<(i32, i32)> = None;
+    let mut index = 0;
+    while index < body.len() && body.len() < LIQUID_LEVEL_MAX_BODY {
+        let (body_x, body_y) = body[index];
+        index += 1;
+        if world.get(body_x, body_y + 1).is_some_and(|pixel| registry.is_replaceable(pixel)) && top.is_none_or(|(_, top_y)| body_y > top_y) {
+            top = Some((body_x, body_y));
+        }
+        for (neighbor_x, neighbor_y) in [(body_x - 1, body_y), (body_x + 1, body_y), (body_x, body_y - 1), (body_x, body_y + 1)] {
+            match world.get(neighbor_x, neighbor_y) {
+                Some(pixel) if pixel == liquid && state.leveled.insert((neighbor_x, neighbor_y)) => {
+                    body.push((neighbor_x, neighbor_y));
+                },
+                Some(pixel) if registry.is_replaceable(pixel) && gap.is_none_or(|(_, gap_y)| neighbor_y < gap_y) => {
+                    gap = Some((neighbor_x, neighbor_y));
+                },
+                _ => {},
+            }
+        }
+    }
+    if let (Some(top), Some(gap)) = (top, gap) {
+        if gap.1 < top.1 {
+            move_pixel(world, state, top, gap);
+        }
+    }
+}
+
+fn step_pixel(world: &mut impl PixelWorld, state: &mut StepState, registry: &MaterialRegistry, x: i32, y: i32) {
+    let Some(pixel) = world.get(x, y) else {
+        return;
+    };
+    if !registry.moves(pixel) || state.moved.contains(&(x, y)) {
+        return;
+    }
+    let left_first = state.left_first;
+    if registry.rises(pixel) {
+        match world.get(x, y + 1) {
+            None => world.wake(x, y),
+            Some(above) if registry.floats_through(pixel, above) => move_pixel(world, state, (x, y), (x, y + 1)),
+            Some(_) => {
+                let spread = Slide { drop: 1, run: LIQUID_FLOW_DISTANCE };
+                if let Some(target) = slide_target(world, spread, x, y, 1, left_first, |other| registry.floats_through(pixel, other)) {
+                    move_pixel(world, state, (x, y), target);
+                }
+            },
+        }
+        return;
+    }
+    match world.get(x, y - 1) {
+        Some(below) if registry.is_replaceable(below) => {
+            let mut looking_at_y = y - 1;
+            loop {
+                match world.get(x, looking_at_y + 1) {
+                    Some(above) if registry.falls(above) => {},
+                    _ => break,
+                }
+                if !world.swap((x, looking_at_y), (x, looking_at_y + 1)) {
+                    state.deferred.push((x, looking_at_y + 1));
+                    break;
+                }
+                state.moved.insert((x, looking_at_y));
+                state.moves.push(((x, looking_at_y), (x, looking_at_y + 1)));
+                looking_at_y += 1;
+            }
+        },
+        Some(below) if registry.get(below).sells_on_contact => {
+            let mut looking_at_y = y - 1;
+            loop {
+                let above = match world.get(x, looking_at_y + 1) {
+                    Some(above) if registry.falls(above) => above,
+                    _ => break,
+                };
+                if !world.set(x, looking_at_y + 1, SKY) {
+                    state.deferred.push((x, looking_at_y + 1));
+                    break;
+                }
+                *state.sold.entry(above).or_insert(0) += 1;
+                state.sales.push(((x, looking_at_y + 1), above));
+                looking_at_y += 1;
+            }
+        },
+        None => world.wake(x, y),
+        Some(below) if registry.sinks_through(pixel, below) => move_pixel(world, state, (x, y), (x, y - 1)),
+        Some(_) if registry.is_liquid(pixel) => {
+            let flow = Slide { drop: 1, run: LIQUID_FLOW_DISTANCE };
+            if let Some(target) = slide_target(world, flow, x, y, -1, left_first, |other| registry.sinks_through(pixel, other)) {
+                move_pixel(world, state, (x, y), target);
+            } else if !state.leveled.contains(&(x, y)) {
+                level_basin(world, state, registry, x, y);
+            }
+        },
+        Some(_) => {
+            let Some(slide) = registry.get(pixel).slide else {
+                return;
+            };
+            if let Some(target) = slide_target(world, slide, x, y, -1, left_first, |other| registry.sinks_through(pixel, other)) {
+                move_pixel(world, state, (x, y), target);
+            }
+        },
+    }
+}
+
+fn step_chunk(world: &mut ChunkNeighborhood, state: &mut StepState, registry: &MaterialRegistry, rect: &DirtyRect) {
+    let width = chunk_size() as usize;
+    for local_y in (rect.min_y..=rect.max_y).rev() {
+        for offset in 0..=rect.max_x - rect.min_x {
+            let local_x = if state.left_first { rect.min_x + offset } else { rect.max_x - offset };
+            let (x_g, y_g) = global_position(world.chunk_pos, local_y * width + local_x);
+            step_pixel(world, state, registry, x_g, y_g);
+        }
+    }
+}
+
+// Updates every awake chunk once. Chunks are split into four checkerboard colors
+// and each color is stepped in parallel, so no two workers ever touch neighboring
+// chunks. Anything that would cross a chunk seam is then redone on the whole map.
+pub fn simulation_tick(chunk_map: &mut ChunkMap, sold: &mut HashMap<u8, u32>, transaction: &mut EditTransaction, registry: &MaterialRegistry, market: &Market, left_first: bool) {
+    let active = std::mem::take(&mut chunk_map.active);
+    let task_pool = ComputeTaskPool::get_or_init(TaskPool::default);
+    let mut deferred = Vec::new();
+    for color in CHECKERBOARD {
+        let mut batch: Vec<((i32, i32), Chunk, DirtyRect)> = active.iter()
+            .filter(|((chunk_x_g, chunk_y_g), _)| (chunk_x_g.rem_euclid(2), chunk_y_g.rem_euclid(2)) == color)
+            .filter_map(|(chunk_pos, rect)| chunk_map.map.remove(chunk_pos).map(|chunk| (*chunk_pos, chunk, *rect)))
+            .collect();
+        if batch.is_empty() {
+            continue;
+        }
+        let (chunks, requested) = (&chunk_map.map, &chunk_map.requested);
+        let results = task_pool.scope(|scope| {
+            for (chunk_pos, chunk, rect) in batch.iter_mut() {
+                scope.spawn(async move {
+                    let mut world = ChunkNeighborhood { chunk_pos: *chunk_pos, chunk, chunks, requested, edits: Vec::new(), wakes: Vec::new() };
+                    let mut state = StepState { left_first, ..Default::default() };
+                    step_chunk(&mut world, &mut state, registry, rect);
+                    (world.edits, world.wakes, state)
+                });
+            }
+        });
+        for (chunk_pos, chunk, _) in batch {
+            chunk_map.map.insert(chunk_pos, chunk);
+        }
+        for (edits, wakes, chunk_state) in results {
+            for ((x_g, y_g), old, new) in edits {
+                chunk_map.mark_changed(x_g, y_g);
+                transaction.record(x_g, y_g, old, new);
+            }
+            for (x_g, y_g) in wakes {
+                chunk_map.wake(x_g, y_g);
+            }
+            record_state(&chunk_state, sold, transaction, market);
+            deferred.extend(chunk_state.deferred);
+        }
+    }
+    deferred.sort_unstable_by_key(|(x, y)| (*y, if left_first { *x } else { -*x }));
+    let mut world = SerialWorld { chunk_map, edits: Vec::new() };
+    let mut state = StepState { left_first, ..Default::default() };
+    for (x_g, y_g) in deferred {
+        step_pixel(&mut world, &mut state, registry, x_g, y_g);
+    }
+    for (x_g, y_g) in state.deferred.iter() {
+        world.chunk_map.wake(*x_g, *y_g);
+    }
+    for ((x_g, y_g), old, new) in world.edits {
+        transaction.record(x_g, y_g, old, new);
+    }
+    record_state(&state, sold, transaction, market);
+}
+
+// Sold pixels are priced individually so undo can refund just the ones an edit caused
+fn record_state(state: &StepState, sold: &mut HashMap<u8, u32>, transaction: &mut EditTransaction, market: &Market) {
+    for (material, quantity) in state.sold.iter() {
+        *sold.entry(*material).or_insert(0) += quantity;
+    }
+    for (position, material) in state.sales.iter() {
+        transaction.sales.insert(*position, market.price(*material));
+    }
+    transaction.moves.extend(state.moves.iter().cloned());
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{chunk::Chunk, color_map::{DIRT1, GRAVEL1, ROCK, SKY, STEAM, WATER}, components::ChunkMap, history::EditTransaction, market::Market, material_registry::MaterialRegistry, simulation::simulation_tick};
+
+    fn settle(chunk_map: &mut ChunkMap, registry: &MaterialRegistry, max_ticks: usize, message: &str) {
+        let mut tick = 0;
+        let market = Market::new(registry, 1);
+        while !chunk_map.active.is_empty() {
+            simulation_tick(chunk_map, &mut HashMap::new(), &mut EditTransaction::default(), registry, &market, tick % 2 == 0);
+            tick += 1;
+            assert!(tick < max_ticks, "{}", message);
+        }
+    }
+
+    fn settle_column(material: u8) -> Vec<i32> {
+        let mut map = HashMap::new();
+        map.insert((0, 0), Chunk::Uniform(SKY));
+        let mut chunk_map = ChunkMap::new(map);
+        let registry = MaterialRegistry::default();
+        chunk_map.fill_rect(-100, -251, 100, -250, ROCK).unwrap();
+        chunk_map.fill_rect(0, -249, 1, -189, material).unwrap();
+        settle(&mut chunk_map, &registry, 2000, "pile never settled");
+        (-100..100).filter(|x| chunk_map.get(*x, -249) == Some(material)).collect()
+    }
+
+    #[test]
+    fn test_piles_settle_at_their_angle_of_repose() {
+        let dirt = settle_column(DIRT1);
+        let gravel = settle_column(GRAVEL1);
+        assert!(gravel.len() > dirt.len() + 4, "gravel {:?} dirt {:?}", gravel, dirt);
+        for pile in [dirt, gravel] {
+            let left = pile.iter().filter(|x| **x < 0).count() as i32;
+            let right = pile.iter().filter(|x| **x > 0).count() as i32;
+            assert!((left - right).abs() <= 2, "lopsided pile {:?}", pile);
+        }
+    }
+
+    #[test]
+    fn test_water_levels_out_between_connected_tubes() {
+        let mut map = HashMap::new();
+        map.insert((0, 0), Chunk::Uniform(SKY));
+        let mut chunk_map = ChunkMap::new(map);
+        let registry = MaterialRegistry::default();
+        chunk_map.fill_rect(-20, -60, 20, -20, ROCK).unwrap();
+        chunk_map.fill_rect(-10, -50, -5, -20, SKY).unwrap();
+        chunk_map.fill_rect(5, -50, 10, -20, SKY).unwrap();
+        chunk_map.fill_rect(-10, -50, 10, -46, SKY).unwrap();
+        chunk_map.fill_rect(-10, -50, -5, -25, WATER).unwrap();
+        chunk_map.fill_rect(-5, -50, 10, -46, WATER).unwrap();
+        settle(&mut chunk_map, &registry, 2000, "water never settled");
+        let level = |tube: std::ops::Range<i32>| (-50..-20).filter(|y| tube.clone().any(|x| chunk_map.get(x, *y) == Some(WATER))).max().unwrap();
+        let (left, right) = (level(-10..-5), level(5..10));
+        assert!((left - right).abs() <= 1, "left tube at {} right tube at {}", left, right);
+        let water = (-60..-20).flat_map(|y| (-20..20).map(move |x| (x, y))).filter(|(x, y)| chunk_map.get(*x, *y) == Some(WATER)).count();
+        assert_eq!(water, 185);
+    }
+
+    #[test]
+    fn test_denser_pixels_sink_and_gases_rise() {
+        let mut map = HashMap::new();
+        map.insert((0, 0), Chunk::Uniform(SKY));
+        let mut chunk_map = ChunkMap::new(map);
+        let registry = MaterialRegistry::default();
+        chunk_map.fill_rect(-1, -31, 2, -15, ROCK).unwrap();
+        chunk_map.fill_rect(0, -30, 1, -16, WATER).unwrap();
+        chunk_map.set(0, -30, STEAM).unwrap();
+        chunk_map.fill_rect(0, -19, 1, -16, GRAVEL1).unwrap();
+        settle(&mut chunk_map, &registry, 500, "column never settled");
+        let column: Vec<u8> = (-30..-16).filter_map(|y| chunk_map.get(0, y)).collect();
+        assert_eq!(column[..3], [GRAVEL1; 3]);
+        assert_eq!(column[3..13], [WATER; 10]);
+        assert_eq!(column[13], STEAM);
+    }
+
+    #[test]
+    fn test_collapse_across_chunk_seams_settles_and_sleeps() {
+        let mut map = HashMap::new();
+        for chunk_x_g in -1..=1 {
+            for chunk_y_g in -1..=0 {
+                map.insert((chunk_x_g, chunk_y_g), Chunk::Uniform(SKY));
+            }
+        }
+        let mut chunk_map = ChunkMap::new(map);
+        let registry = MaterialRegistry::default();
+        chunk_map.fill_rect(-400, -330, 400, -329, ROCK).unwrap();
+        chunk_map.fill_rect(-310, -280, -290, -220, GRAVEL1).unwrap();
+        assert!(chunk_map.active.contains_key(&(-1, 0)) && chunk_map.active.contains_key(&(0, 0)) && !chunk_map.active.contains_key(&(1, 0)));
+        settle(&mut chunk_map, &registry, 5000, "collapse never settled");
+        let count = |material: u8| chunk_map.pixels_in_rect(-400, -329, 400, 300).filter(|(_, _, pixel)| *pixel == Some(material)).count();
+        assert_eq!(count(GRAVEL1), 20 * 60);
+        assert!(chunk_map.pixels_in_rect(-320, -329, -280, -328).any(|(_, _, pixel)| pixel == Some(GRAVEL1)));
+        chunk_map.set(0, -400, SKY).unwrap();
+        assert_eq!(chunk_map.active.keys().collect::<Vec<_>>(), vec![&(0, -1)]);
+    }
+}
@@ -0,0 +1,146 @@
+use std::fs;
+use std::sync::OnceLock;
+
+use bevy::log::info;
+use noise::{NoiseFn, Perlin};
+use serde::Deserialize;
+
+use crate::constants::TERRAIN_CONFIG_PATH;
+use crate::material_registry::MaterialRegistry;
+use crate::util::arg_value;
+
+const DEFAULT_TERRAIN_CONFIG: &str = include_str!("../assets/terrain.ron");
+
+static TERRAIN_CONFIG: OnceLock<TerrainConfig> = OnceLock::new();
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct NoiseSource {
+    pub octaves: u32,
+    pub frequency: f64,
+    pub amplitude: f64,
+    #[serde(default)]
+    pub offset: f64,
+}
+
+impl NoiseSource {
+    pub fn sample(&self, perlin: &Perlin, x: f64) -> f64 {
+        let (mut total, mut weight, mut frequency, mut amplitude) = (0., 0., self.frequency, 1.);
+        for _ in 0..self.octaves {
+            total += perlin.get([x * frequency, self.offset]) * amplitude;
+            weight += amplitude;
+            frequency *= 2.;
+            amplitude *= 0.5;
+        }
+        total / weight * self.amplitude
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Layer {
+    pub materials: Vec<(String, u32)>,
+    pub base_height: f64,
+    pub noise: NoiseSource,
+    #[serde(skip)]
+    pub palette: Vec<u8>,
+}
+
+impl Layer {
+    pub fn height(&self, perlin: &Perlin, global_x: i32) -> f64 {
+        self.base_height + self.noise.sample(perlin, global_x as f64)
+    }
+}
+
+#[derive(Deserialize, Default, Clone, Copy, Debug)]
+pub struct Vegetation {
+    pub trees: f32,
+    pub bushes: f32,
+    pub tall_grass: f32,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Biome {
+    pub name: String,
+    pub layers: Vec<Layer>,
+    #[serde(default)]
+    pub vegetation: Vegetation,
+    #[serde(default)]
+    pub lakes: f32,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct TerrainConfig {
+    pub biome_noise: NoiseSource,
+    pub biome_noise_range: f64,
+    pub biome_transition_width: f64,
+    pub biomes: Vec<Biome>,
+}
+
+pub fn parse_terrain_config(source: &str, registry: &MaterialRegistry) -> Result<TerrainConfig, String> {
+    let mut config: TerrainConfig = ron::from_str(source).map_err(|err| err.to_string())?;
+    if config.biomes.len() < 2 {
+        return Err("at least two biomes are needed to blend between".to_string());
+    }
+    if config.biome_noise_range <= 0. || config.biome_transition_width <= 0. {
+        return Err("biome_noise_range and biome_transition_width must be positive".to_string());
+    }
+    for biome in config.biomes.iter_mut() {
+        if biome.layers.len() < 2 {
+            return Err(format!("biome {} needs a surface layer and a rock layer", biome.name));
+        }
+        for (index, layer) in biome.layers.iter_mut().enumerate() {
+            if layer.noise.octaves == 0 {
+                return Err(format!("layer {} of biome {} needs at least one noise octave", index, biome.name));
+            }
+            for (name, weight) in layer.materials.iter() {
+                let material = registry.by_name(name).ok_or_else(|| format!("unknown material {} in biome {}", name, biome.name))?;
+                layer.palette.extend(std::iter::repeat_n(material, *weight as usize));
+            }
+            if layer.palette.is_empty() {
+                return Err(format!("layer {} of biome {} has no weighted materials", index, biome.name));
+            }
+        }
+    }
+    Ok(config)
+}
+
+pub fn load_terrain_config(registry: &MaterialRegistry) {
+    let config = match arg_value("--terrain-config") {
+        Some(path) => {
+            let source = fs::read_to_string(&path).unwrap_or_else(|err| panic!("Could not read terrain config {}: {}", path, err));
+            parse_terrain_config(&source, registry).unwrap_or_else(|err| panic!("Could not parse terrain config {}: {}", path, err))
+        },
+        None => match fs::read_to_string(TERRAIN_CONFIG_PATH) {
+            Ok(source) => parse_terrain_config(&source, registry).unwrap_or_else(|err| panic!("Could not parse terrain config {}: {}", TERRAIN_CONFIG_PATH, err)),
+            Err(_) => {
+                info!("No terrain config at {}, using the built in layers", TERRAIN_CONFIG_PATH);
+                parse_terrain_config(DEFAULT_TERRAIN_CONFIG, registry).unwrap()
+            },
+        },
+    };
+    if TERRAIN_CONFIG.set(config).is_err() {
+        panic!("Terrain config must be loaded before it is first read");
+    }
+}
+
+pub fn terrain_config() -> &'static TerrainConfig {
+    TERRAIN_CONFIG.get_or_init(|| parse_terrain_config(DEFAULT_TERRAIN_CONFIG, &MaterialRegistry::default()).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{color_map::{DIRT1, DIRT2}, material_registry::MaterialRegistry, terrain_config::parse_terrain_config};
+
+    #[test]
+    fn test_parse_terrain_config() {
+        let layer = |materials: &str| format!("Layer(materials: {}, base_height: 0.0, noise: NoiseSource(octaves: 2, frequency: 0.01, amplitude: 5.0))", materials);
+        let config = |materials: &str| format!(
+            "TerrainConfig(biome_noise: NoiseSource(octaves: 1, frequency: 0.001, amplitude: 1.0), biome_noise_range: 0.5, biome_transition_width: 0.5, biomes: [Biome(name: \"a\", layers: [{0}, {0}]), Biome(name: \"b\", layers: [{0}, {0}])])",
+            layer(materials),
+        );
+        let registry = MaterialRegistry::default();
+        let parsed = parse_terrain_config(&config("[(\"DIRT1\", 2), (\"DIRT2\", 1)]"), &registry).unwrap();
+        assert_eq!(parsed.biomes[1].layers[0].palette, vec![DIRT1, DIRT1, DIRT2]);
+        assert!(parse_terrain_config(&config("[(\"MUD\", 1)]"), &registry).is_err());
+        assert!(parse_terrain_config(&config("[]"), &registry).is_err());
+    }
+}
@@ -0,0 +1,273 @@
+use std::fmt;
+
+use crate::chunk::DirtyRect;
+use crate::components::ChunkMap;
+use crate::constants::{chunk_size, SIMULATION_WAKE_MARGIN};
+use crate::material_registry::MaterialRegistry;
+use crate::util::{distance, flatten_index_standard_grid, get_chunk_x_g, get_chunk_y_g, get_local_x, get_local_y, local_to_global_x};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ChunkNotLoaded {
+    pub chunk_x_g: i32,
+    pub chunk_y_g: i32,
+}
+
+impl fmt::Display for ChunkNotLoaded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "chunk {}, {} is not loaded", self.chunk_x_g, self.chunk_y_g)
+    }
+}
+
+pub fn locate(x_g: i32, y_g: i32) -> ((i32, i32), usize) {
+    let local_index = flatten_index_standard_grid(&get_local_x(x_g), &get_local_y(y_g), chunk_size() as usize);
+    ((get_chunk_x_g(x_g), get_chunk_y_g(y_g)), local_index)
+}
+
+pub fn global_position(chunk_pos: (i32, i32), local_index: usize) -> (i32, i32) {
+    let (local_x, local_y) = (local_index % chunk_size() as usize, local_index / chunk_size() as usize);
+    (local_to_global_x(chunk_pos.0, local_x), chunk_pos.1 * chunk_size() as i32 + chunk_size() as i32 / 2 - 1 - local_y as i32)
+}
+
+impl ChunkMap {
+    pub fn request(&self, chunk_pos: (i32, i32)) {
+        self.requested.lock().unwrap().insert(chunk_pos);
+    }
+
+    pub fn take_requested(&mut self) -> Vec<(i32, i32)> {
+        self.requested.get_mut().unwrap().drain().collect()
+    }
+
+    pub fn get(&self, x_g: i32, y_g: i32) -> Option<u8> {
+        let (chunk_pos, local_index) = locate(x_g, y_g);
+        match self.map.get(&chunk_pos) {
+            Some(chunk) => Some(chunk.get(local_index)),
+            None => {
+                self.request(chunk_pos);
+                None
+            }
+        }
+    }
+
+    pub fn try_get(&self, x_g: i32, y_g: i32) -> Result<u8, ChunkNotLoaded> {
+        self.get(x_g, y_g).ok_or(ChunkNotLoaded { chunk_x_g: get_chunk_x_g(x_g), chunk_y_g: get_chunk_y_g(y_g) })
+    }
+
+    pub fn set(&mut self, x_g: i32, y_g: i32, pixel: u8) -> Result<(), ChunkNotLoaded> {
+        let (chunk_pos, local_index) = locate(x_g, y_g);
+        match self.map.get_mut(&chunk_pos) {
+            Some(chunk) => {
+                chunk.set(local_index, pixel);
+                self.mark_changed(x_g, y_g);
+                Ok(())
+            },
+            None => {
+                self.request(chunk_pos);
+                Err(ChunkNotLoaded { chunk_x_g: chunk_pos.0, chunk_y_g: chunk_pos.1 })
+            }
+        }
+    }
+
+    pub fn mark_changed(&mut self, x_g: i32, y_g: i32) {
+        let (chunk_pos, local_index) = locate(x_g, y_g);
+        self.clear_damage(chunk_pos, local_index);
+        self.mark_dirty(x_g, y_g);
+        self.wake(x_g, y_g);
+    }
+
+    pub fn wake(&mut self, x_g: i32, y_g: i32) {
+        let half = chunk_size() as i32 / 2;
+        let (left, bottom, right, top) = (x_g - SIMULATION_WAKE_MARGIN, y_g - SIMULATION_WAKE_MARGIN, x_g + SIMULATION_WAKE_MARGIN, y_g + SIMULATION_WAKE_MARGIN);
+        for chunk_x_g in get_chunk_x_g(left)..=get_chunk_x_g(right) {
+            for chunk_y_g in get_chunk_y_g(bottom)..=get_chunk_y_g(top) {
+                if !self.map.contains_key(&(chunk_x_g, chunk_y_g)) {
+                    continue;
+                }
+                let (chunk_left, chunk_bottom) = (chunk_x_g * chunk_size() as i32 - half, chunk_y_g * chunk_size() as i32 - half);
+                let clamp_x = |x: i32| get_local_x(x.clamp(chunk_left, chunk_left + chunk_size() as i32 - 1));
+                let clamp_y = |y: i32| get_local_y(y.clamp(chunk_bottom, chunk_bottom + chunk_size() as i32 - 1));
+                let rect = DirtyRect { min_x: clamp_x(left), min_y: clamp_y(top), max_x: clamp_x(right), max_y: clamp_y(bottom) };
+                self.active.entry((chunk_x_g, chunk_y_g))
+                    .and_modify(|active| {
+                        active.include(rect.min_x, rect.min_y);
+                        active.include(rect.max_x, rect.max_y);
+                    })
+                    .or_insert(rect);
+            }
+        }
+    }
+
+    pub fn swap(&mut self, a: (i32, i32), b: (i32, i32)) -> Result<(), ChunkNotLoaded> {
+        let pixel_a = self.try_get(a.0, a.1)?;
+        let pixel_b = self.try_get(b.0, b.1)?;
+        self.set(a.0, a.1, pixel_b)?;
+        self.set(b.0, b.1, pixel_a)
+    }
+
+    pub fn fill_rect(&mut self, left: i32, bottom: i32, right: i32, top: i32, pixel: u8) -> Result<(), ChunkNotLoaded> {
+        let mut result = Ok(());
+        for y_g in bottom..top {
+            for x_g in left..right {
+                if let Err(err) = self.set(x_g, y_g, pixel) {
+                    result = Err(err);
+                }
+            }
+        }
+        result
+    }
+
+    pub fn fill_circle(&mut self, center_x_g: i32, center_y_g: i32, radius: f32, pixel: u8) -> Result<(), ChunkNotLoaded> {
+        let mut result = Ok(());
+        for (x_g, y_g) in circle_coords(center_x_g, center_y_g, radius) {
+            if let Err(err) = self.set(x_g, y_g, pixel) {
+                result = Err(err);
+            }
+        }
+        result
+    }
+
+    pub fn damage(&self, x_g: i32, y_g: i32) -> f32 {
+        let (chunk_pos, local_index) = locate(x_g, y_g);
+        self.damage.get(&chunk_pos).and_then(|damage| damage.get(&local_index)).copied().unwrap_or(0.)
+    }
+
+    pub fn add_damage(&mut self, x_g: i32, y_g: i32, amount: f32) -> f32 {
+        let (chunk_pos, local_index) = locate(x_g, y_g);
+        let damage = self.damage.entry(chunk_pos).or_default().entry(local_index).or_insert(0.);
+        *damage += amount;
+        let total = *damage;
+        self.mark_dirty(x_g, y_g);
+        total
+    }
+
+    fn clear_damage(&mut self, chunk_pos: (i32, i32), local_index: usize) {
+        if let Some(damage) = self.damage.get_mut(&chunk_pos) {
+            if damage.remove(&local_index).is_some() && damage.is_empty() {
+                self.damage.remove(&chunk_pos);
+            }
+        }
+    }
+
+    pub fn damage_rect_data(&self, chunk_pos: (i32, i32), rect: &DirtyRect) -> Vec<u8> {
+        let width = rect.max_x - rect.min_x + 1;
+        let mut data = vec![0; width * (rect.max_y - rect.min_y + 1)];
+        let Some(damage) = self.damage.get(&chunk_pos) else {
+            return data;
+        };
+        for (local_index, fraction) in damage.iter() {
+            let (x, y) = (local_index % chunk_size() as usize, local_index / chunk_size() as usize);
+            if (rect.min_x..=rect.max_x).contains(&x) && (rect.min_y..=rect.max_y).contains(&y) {
+                data[(y - rect.min_y) * width + x - rect.min_x] = (fraction.clamp(0., 1.) * 255.).round() as u8;
+            }
+        }
+        data
+    }
+
+    fn peek(&self, x_g: i32, y_g: i32) -> Option<u8> {
+        let (chunk_pos, local_index) = locate(x_g, y_g);
+        self.map.get(&chunk_pos).map(|chunk| chunk.get(local_index))
+    }
+
+    pub fn unsupported_pixels(&self, chunk_pos: (i32, i32), registry: &MaterialRegistry) -> Vec<(i32, i32)> {
+        let Some(chunk) = self.map.get(&chunk_pos) else {
+            return Vec::new();
+        };
+        let width = chunk_size() as usize;
+        let mut unsupported = Vec::new();
+        for local_index in 0..width * width {
+            let pixel = chunk.get(local_index);
+            if !registry.moves(pixel) {
+                continue;
+            }
+            let (x_g, y_g) = global_position(chunk_pos, local_index);
+            let displaces = if registry.rises(pixel) {
+                let above = if local_index >= width {
+                    Some(chunk.get(local_index - width))
+                } else {
+                    self.peek(x_g, y_g + 1)
+                };
+                above.is_some_and(|above| registry.floats_through(pixel, above))
+            } else {
+                let below = if local_index + width < width * width {
+                    Some(chunk.get(local_index + width))
+                } else {
+                    self.peek(x_g, y_g - 1)
+                };
+                below.is_some_and(|below| registry.sinks_through(pixel, below))
+            };
+            if displaces {
+                unsupported.push((x_g, y_g));
+            }
+        }
+        for local_x in 0..width {
+            let (top_x_g, top_y_g) = global_position(chunk_pos, local_x);
+            if self.peek(top_x_g, top_y_g + 1).is_some_and(|above| registry.sinks_through(above, chunk.get(local_x))) {
+                unsupported.push((top_x_g, top_y_g + 1));
+            }
+            let (bottom_x_g, bottom_y_g) = global_position(chunk_pos, (width - 1) * width + local_x);
+            if self.peek(bottom_x_g, bottom_y_g - 1).is_some_and(|below| registry.floats_through(below, chunk.get((width - 1) * width + local_x))) {
+                unsupported.push((bottom_x_g, bottom_y_g - 1));
+            }
+        }
+        unsupported
+    }
+
+    pub fn pixels_in_rect(&self, left: i32, bottom: i32, right: i32, top: i32) -> impl Iterator<Item = (i32, i32, Option<u8>)> + '_ {
+        (bottom..top).flat_map(move |y_g| (left..right).map(move |x_g| (x_g, y_g, self.get(x_g, y_g))))
+    }
+
+    pub fn pixels_in_circle(&self, center_x_g: i32, center_y_g: i32, radius: f32) -> impl Iterator<Item = (i32, i32, Option<u8>)> + '_ {
+        circle_coords(center_x_g, center_y_g, radius).map(move |(x_g, y_g)| (x_g, y_g, self.get(x_g, y_g)))
+    }
+}
+
+pub fn circle_coords(center_x_g: i32, center_y_g: i32, radius: f32) -> impl Iterator<Item = (i32, i32)> {
+    let extent = radius.ceil() as i32;
+    (center_y_g - extent..center_y_g + extent)
+        .flat_map(move |y_g| (center_x_g - extent..center_x_g + extent).map(move |x_g| (x_g, y_g)))
+        .filter(move |(x_g, y_g)| distance(*x_g, *y_g, center_x_g, center_y_g) < radius)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{chunk::Chunk, color_map::{DIRT1, ROCK, SKY}, components::ChunkMap, constants::chunk_size, material_registry::MaterialRegistry, terrain_world::ChunkNotLoaded};
+
+    fn single_chunk_map() -> ChunkMap {
+        let mut map = HashMap::new();
+        map.insert((0, 0), Chunk::Uniform(SKY));
+        ChunkMap::new(map)
+    }
+
+    #[test]
+    fn test_set_and_swap() {
+        let mut chunk_map = single_chunk_map();
+        chunk_map.set(3, 4, DIRT1).unwrap();
+        assert_eq!(chunk_map.get(3, 4), Some(DIRT1));
+        chunk_map.swap((3, 4), (3, 3)).unwrap();
+        assert_eq!(chunk_map.get(3, 4), Some(SKY));
+        assert_eq!(chunk_map.get(3, 3), Some(DIRT1));
+        assert!(chunk_map.edited.contains(&(0, 0)));
+    }
+
+    #[test]
+    fn test_missing_chunk_is_requested() {
+        let mut chunk_map = single_chunk_map();
+        let outside = chunk_size() as i32 * 2;
+        assert_eq!(chunk_map.get(outside, 0), None);
+        assert!(matches!(chunk_map.set(outside, 0, DIRT1), Err(ChunkNotLoaded { .. })));
+        assert_eq!(chunk_map.take_requested().len(), 1);
+        assert!(chunk_map.take_requested().is_empty());
+    }
+
+    #[test]
+    fn test_unsupported_pixels() {
+        let mut chunk_map = single_chunk_map();
+        chunk_map.set(3, 4, DIRT1).unwrap();
+        chunk_map.set(5, 4, DIRT1).unwrap();
+        chunk_map.set(5, 3, ROCK).unwrap();
+        chunk_map.set(7, 4, ROCK).unwrap();
+        assert_eq!(chunk_map.unsupported_pixels((0, 0), &MaterialRegistry::default()), vec![(3, 4)]);
+        assert!(chunk_map.take_requested().is_empty());
+    }
+}
@@ -0,0 +1,143 @@
+use std::time::Duration;
+
+use bevy::app::FixedMain;
+use bevy::input::ButtonInput;
+use bevy::log::info;
+use bevy::prelude::{KeyCode, Res, ResMut, Resource, World};
+use bevy::time::{Fixed, Time, Virtual};
+
+use crate::constants::{DEFAULT_SIMULATION_TICK_RATE, MAX_SIMULATION_CATCH_UP_STEPS, MAX_TIME_SCALE, MIN_TIME_SCALE};
+use crate::util::{arg_value, has_arg};
+
+#[derive(Resource, Debug)]
+pub struct TimeScale {
+    pub scale: f64,
+    pub paused: bool,
+    pub pending_steps: u32,
+}
+
+impl Default for TimeScale {
+    fn default() -> TimeScale {
+        TimeScale { scale: 1., paused: false, pending_steps: 0 }
+    }
+}
+
+impl TimeScale {
+    pub fn from_args() -> TimeScale {
+        let scale: f64 = arg_value("--time-scale").map(|value| value.parse().expect("--time-scale must be a number")).unwrap_or(1.);
+        TimeScale { scale: scale.clamp(MIN_TIME_SCALE, MAX_TIME_SCALE), paused: has_arg("--paused"), pending_steps: 0 }
+    }
+}
+
+pub fn simulation_tick_rate() -> f64 {
+    let tick_rate: f64 = arg_value("--tick-rate").map(|value| value.parse().expect("--tick-rate must be a number")).unwrap_or(DEFAULT_SIMULATION_TICK_RATE);
+    if tick_rate <= 0. {
+        panic!("Tick rate must be positive, got {}", tick_rate);
+    }
+    tick_rate
+}
+
+// Virtual time is clamped before it is scaled, so the limit is divided by the scale
+// to keep the number of fixed steps per frame the same at every speed.
+pub fn catch_up_limit(timestep: Duration, scale: f64) -> Duration {
+    timestep.mul_f64(MAX_SIMULATION_CATCH_UP_STEPS as f64 / scale)
+}
+
+pub fn update_time_scale(keys: Res<ButtonInput<KeyCode>>, mut time_scale: ResMut<TimeScale>) {
+    if keys.just_pressed(KeyCode::KeyP) {
+        time_scale.paused = !time_scale.paused;
+        time_scale.pending_steps = 0;
+        info!("Simulation {}", if time_scale.paused { "paused" } else { "resumed" });
+    }
+    if keys.just_pressed(KeyCode::Period) && time_scale.paused {
+        time_scale.pending_steps += 1;
+    }
+    if keys.just_pressed(KeyCode::BracketLeft) {
+        time_scale.scale = (time_scale.scale / 2.).max(MIN_TIME_SCALE);
+        info!("Time scale {}", time_scale.scale);
+    }
+    if keys.just_pressed(KeyCode::BracketRight) {
+        time_scale.scale = (time_scale.scale * 2.).min(MAX_TIME_SCALE);
+        info!("Time scale {}", time_scale.scale);
+    }
+}
+
+pub fn apply_time_scale(time_scale: Res<TimeScale>, mut virtual_time: ResMut<Time<Virtual>>, fixed_time: Res<Time<Fixed>>) {
+    virtual_time.set_relative_speed_f64(time_scale.scale);
+    virtual_time.set_max_delta(catch_up_limit(fixed_time.timestep(), time_scale.scale));
+    if time_scale.paused {
+        virtual_time.pause();
+    } else {
+        virtual_time.unpause();
+    }
+}
+
+pub fn step_paused_simulation(world: &mut World) {
+    let paused = world.resource::<Time<Virtual>>().is_paused();
+    let mut time_scale = world.resource_mut::<TimeScale>();
+    if !paused || time_scale.pending_steps == 0 {
+        return;
+    }
+    time_scale.pending_steps -= 1;
+    let mut step = Time::<()>::default();
+    step.advance_by(world.resource::<Time<Fixed>>().timestep());
+    let previous = std::mem::replace(&mut *world.resource_mut::<Time>(), step);
+    world.run_schedule(FixedMain);
+    *world.resource_mut::<Time>() = previous;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::app::{App, FixedUpdate, Update};
+    use bevy::input::ButtonInput;
+    use bevy::prelude::{IntoSystemConfigs, KeyCode, ResMut, Resource};
+    use bevy::time::{Fixed, Time, TimePlugin, TimeUpdateStrategy};
+
+    use crate::{constants::MAX_SIMULATION_CATCH_UP_STEPS, time_control::{apply_time_scale, step_paused_simulation, update_time_scale, TimeScale}};
+
+    #[derive(Resource, Default)]
+    struct Ticks(u32);
+
+    fn tap(app: &mut App, key: KeyCode) {
+        app.world_mut().resource_mut::<ButtonInput<KeyCode>>().press(key);
+        app.update();
+        app.world_mut().resource_mut::<ButtonInput<KeyCode>>().clear();
+        app.world_mut().resource_mut::<ButtonInput<KeyCode>>().release(key);
+    }
+
+    #[test]
+    fn test_fixed_steps_catch_up_pause_and_step() {
+        let mut app = App::new();
+        app.add_plugins(TimePlugin)
+            .insert_resource(Time::<Fixed>::from_hz(100.))
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs(1)))
+            .init_resource::<TimeScale>()
+            .init_resource::<ButtonInput<KeyCode>>()
+            .init_resource::<Ticks>()
+            .add_systems(FixedUpdate, |mut ticks: ResMut<Ticks>| ticks.0 += 1)
+            .add_systems(Update, (update_time_scale, apply_time_scale, step_paused_simulation).chain());
+        app.update();
+        app.world_mut().resource_mut::<Ticks>().0 = 0;
+        let ticks = |app: &App| app.world().resource::<Ticks>().0;
+        for _ in 0..3 {
+            app.update();
+        }
+        assert_eq!(ticks(&app), 3 * MAX_SIMULATION_CATCH_UP_STEPS);
+        app.world_mut().resource_mut::<TimeScale>().scale = 4.;
+        app.update();
+        app.update();
+        assert_eq!(ticks(&app), 5 * MAX_SIMULATION_CATCH_UP_STEPS);
+        tap(&mut app, KeyCode::KeyP);
+        let paused_at = ticks(&app);
+        app.update();
+        app.update();
+        assert_eq!(ticks(&app), paused_at);
+        tap(&mut app, KeyCode::Period);
+        assert_eq!(ticks(&app), paused_at + 1);
+        tap(&mut app, KeyCode::KeyP);
+        app.update();
+        assert!(ticks(&app) > paused_at + 1);
+    }
+}
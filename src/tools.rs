@@ -1,8 +1,6 @@
-use std::collections::HashMap;
+use bevy::{asset::{Assets, Handle}, math::Vec2, prelude::{Changed, Commands, Component, Entity, Image, Mesh, Query, Rectangle, Res, ResMut, SpatialBundle, Transform, Visibility, With, Without}, sprite::Mesh2dHandle};
 
-use bevy::{asset::Assets, ecs::event::EventWriter, math::Vec2, prelude::{Camera, Commands, Component, GlobalTransform, Image, Mesh, Query, Rectangle, ResMut, Transform, Visibility, With, Without}, sprite::MaterialMesh2dBundle, window::{PrimaryWindow, Window}};
-
-use crate::{chunk_generator::NewChunkEvent, color_map::{apply_gamma_correction, gravel_variant_pmf, CLEAR, LIGHT, RAW_DECODER_DATA, RED, ROCK, SHOVEL_ABLE, SKY, STEEL, TRANSLUCENT_GREY, WHITE}, components::{Bool, CameraTag, ChunkMap, ContentList, GravityCoords, PlayerTag, Velocity}, constants::{CHUNK_SIZE, CURSOR_BORDER_WIDTH, CURSOR_ORBITAL_RADIUS, CURSOR_RADIUS, HOE_HEIGHT, HOE_WIDTH, MAX_SHOVEL_CAPACITY}, materials::DefaultMaterial, util::{distance, flatten_index, flatten_index_standard_grid, get_chunk_x_g, get_chunk_y_g, get_local_x, get_local_y, grid_to_image}};
+use crate::{color_map::{CLEAR, RED, SKY, STEEL, TRANSLUCENT_GREY, WHITE}, components::{Bool, ChunkMap, ContentList, PlayerTag, Velocity}, constants::{CURSOR_BORDER_WIDTH, CURSOR_ORBITAL_RADIUS, CURSOR_RADIUS, HOE_HEIGHT, HOE_WIDTH, MAX_SHOVEL_CAPACITY, PICKAXE_FALLOFF, PICKAXE_POWER}, history::EditTransaction, material_registry::MaterialRegistry, materials::DefaultMaterial, mouse_controller::CursorPosition, terrain_world::circle_coords, util::{distance, flatten_index, grid_to_image}};
 
 #[derive(Component)]
 pub struct HoeTag;
@@ -26,64 +24,45 @@ pub struct CurrentTool{
     pub tool: Tool
 }
 
-pub fn spawn_tools(
-    mut commands: Commands,
-    mut materials: ResMut<Assets<DefaultMaterial>>,
-    mut images: ResMut<Assets<Image>>,
-    mut meshes: ResMut<Assets<Mesh>>,
-) {
-    let shovel_grid = generate_shovel_grid();
-    let pickaxe_grid = generate_pickaxe_grid();
-    let hoe_grid = generate_hoe_grid();
-    let shovel_image = grid_to_image(&shovel_grid, CURSOR_RADIUS as u32 * 2, CURSOR_RADIUS as u32 * 2, None);
-    let pickaxe_image = grid_to_image(&pickaxe_grid, CURSOR_RADIUS as u32 * 2, CURSOR_RADIUS as u32 * 2, None);
-    let hoe_image = grid_to_image(&hoe_grid, HOE_WIDTH as u32, HOE_HEIGHT as u32, None);
+pub fn spawn_tools(mut commands: Commands) {
     commands.spawn(HoeTag)
-            .insert(MaterialMesh2dBundle {
-                material: materials.add(DefaultMaterial {
-                    color_map_handle: images.add(hoe_image),
-                    size: Vec2::new(HOE_WIDTH as f32, HOE_HEIGHT as f32),
-                    decoder: apply_gamma_correction(RAW_DECODER_DATA),   
-                }),
-                mesh: meshes
-                .add(Rectangle {
-                    half_size: Vec2::new((HOE_WIDTH/2) as f32, (HOE_HEIGHT/2) as f32),
-                })
-                .into(),
-                visibility: Visibility::Hidden,
-                ..Default::default()
-            })
+            .insert(SpatialBundle { visibility: Visibility::Hidden, ..Default::default() })
             .insert(Bool { bool: false });
     commands.spawn(ShovelTag)
-            .insert(MaterialMesh2dBundle {
-                material: materials.add(DefaultMaterial {
-                    color_map_handle: images.add(shovel_image),
-                    size: Vec2::new((CURSOR_RADIUS * 2) as f32, (CURSOR_RADIUS * 2) as f32),
-                    decoder: apply_gamma_correction(RAW_DECODER_DATA),
-                }),
-                mesh: meshes
-                .add(Rectangle {
-                    half_size: Vec2::new((CURSOR_RADIUS) as f32, (CURSOR_RADIUS) as f32),
-                })
-                .into(),
-                ..Default::default()
-            })
+            .insert(SpatialBundle::default())
             .insert(ContentList { contents: Vec::new() });
     commands.spawn(PickaxeTag)
-            .insert(MaterialMesh2dBundle {
-                material: materials.add(DefaultMaterial {
-                    color_map_handle: images.add(pickaxe_image),
-                    size: Vec2::new((CURSOR_RADIUS * 2) as f32, (CURSOR_RADIUS * 2) as f32),
-                    decoder: apply_gamma_correction(RAW_DECODER_DATA),               
-                }),
-                mesh: meshes
-                .add(Rectangle {
-                    half_size: Vec2::new((CURSOR_RADIUS) as f32, (CURSOR_RADIUS) as f32),
-                })
-                .into(),
-                visibility: Visibility::Hidden,
-                ..Default::default()
-            });
+            .insert(SpatialBundle { visibility: Visibility::Hidden, ..Default::default() });
+}
+
+pub fn spawn_tool_visuals(
+    mut commands: Commands,
+    hoe_query: Query<Entity, With<HoeTag>>,
+    shovel_query: Query<Entity, With<ShovelTag>>,
+    pickaxe_query: Query<Entity, With<PickaxeTag>>,
+    mut materials: ResMut<Assets<DefaultMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    registry: Res<MaterialRegistry>,
+) {
+    let shovel_image = grid_to_image(&generate_shovel_grid(), CURSOR_RADIUS as u32 * 2, CURSOR_RADIUS as u32 * 2, None);
+    let pickaxe_image = grid_to_image(&generate_pickaxe_grid(), CURSOR_RADIUS as u32 * 2, CURSOR_RADIUS as u32 * 2, None);
+    let hoe_image = grid_to_image(&generate_hoe_grid(), HOE_WIDTH as u32, HOE_HEIGHT as u32, None);
+    for (entity, image, width, height) in [
+        (hoe_query.get_single().unwrap(), hoe_image, HOE_WIDTH, HOE_HEIGHT),
+        (shovel_query.get_single().unwrap(), shovel_image, CURSOR_RADIUS * 2, CURSOR_RADIUS * 2),
+        (pickaxe_query.get_single().unwrap(), pickaxe_image, CURSOR_RADIUS * 2, CURSOR_RADIUS * 2),
+    ] {
+        commands.entity(entity)
+                .insert(materials.add(DefaultMaterial {
+                    color_map_handle: images.add(image),
+                    size: Vec2::new(width as f32, height as f32),
+                    decoder: registry.decoder(),
+                }))
+                .insert(Mesh2dHandle(meshes.add(Rectangle {
+                    half_size: Vec2::new((width/2) as f32, (height/2) as f32),
+                })));
+    }
 }
 
 fn generate_shovel_grid() -> Vec<u8>{
@@ -131,20 +110,20 @@ fn generate_hoe_grid() -> Vec<u8> {
 }
 
 pub fn update_tool(
-    q_windows: Query<&Window, With<PrimaryWindow>>,
+    cursor_position: Res<CursorPosition>,
     mut player_query: Query<(&mut Transform, &mut Velocity), (With<PlayerTag>, Without<ShovelTag>)>,
     mut shovel_query: Query<&mut Transform, (With<ShovelTag>, (Without<PlayerTag>, Without<PickaxeTag>))>,
     mut pickaxe_query: Query<&mut Transform, (With<PickaxeTag>, (Without<PlayerTag>, Without<ShovelTag>))>,
     mut hoe_query: Query<&mut Transform, (With<HoeTag>, (Without<PlayerTag>, Without<ShovelTag>, Without<PickaxeTag>))>,
     current_tool_query: Query<&CurrentTool>,
     is_hoe_locked_query: Query<&Bool, With<HoeTag>>,
-    mut chunk_map_query: Query<&mut ChunkMap>,
-    q_camera: Query<(&Camera, &GlobalTransform), With<CameraTag>>,
+    chunk_map_query: Query<&ChunkMap>,
+    registry: Res<MaterialRegistry>,
 ) {
     let player = player_query.get_single_mut().unwrap();
     let current_tool = current_tool_query.get_single().unwrap();
     let mut tool_position;
-    let chunk_map = chunk_map_query.get_single_mut().unwrap();
+    let chunk_map = chunk_map_query.get_single().unwrap();
     let hoe_is_locked = is_hoe_locked_query.get_single().unwrap();
     match current_tool.tool {
         Tool::Shovel => {
@@ -160,66 +139,40 @@ pub fn update_tool(
             return
         }
     }
-    let (camera, camera_transform) = q_camera.single();
-    if let Ok(window) = q_windows.get_single() {
-        if let Some(cursor) = window.cursor_position() {
-            if let Some(ray) = camera.viewport_to_world(camera_transform, cursor) {
-                let position_c = ray.origin.truncate();
-                let angle = (position_c.y - player.0.translation.y).atan2(position_c.x - player.0.translation.x);
-                let mut potential_x = player.0.translation.x;
-                let mut potential_y = player.0.translation.y;
-                let dy = angle.sin();
-                let dx = angle.cos();
-                let mut local_x = get_local_x(potential_x as i32);
-                let mut local_y = get_local_y(potential_y as i32);
-                let mut local_index = flatten_index_standard_grid(&local_x, &local_y, CHUNK_SIZE as usize);
-                let (chunk_x_g, chunk_y_g) = (get_chunk_x_g(potential_x as i32), get_chunk_y_g(potential_y as i32));
-                match chunk_map.map.get(&(chunk_x_g, chunk_y_g)) {
-                    Some(mut chunk) => {
-                        while chunk[local_index] == SKY || chunk[local_index] == LIGHT {
-                            potential_x += dx as f32;
-                            potential_y += dy as f32;
-                            let distance_from_player_to_potential = distance(player.0.translation.x as i32, player.0.translation.y as i32, potential_x as i32, potential_y as i32);
-                            if distance_from_player_to_potential > CURSOR_ORBITAL_RADIUS {
-                                break
-                            }
-                            if distance(potential_x as i32, potential_y as i32, position_c.x as i32, position_c.y as i32) < 2. {
-                                break
-                            }
-                            local_x = get_local_x(potential_x as i32);
-                            local_y = get_local_y(potential_y as i32);
-                            local_index = flatten_index_standard_grid(&local_x, &local_y, CHUNK_SIZE as usize); 
-                            let chunk_x_g = get_chunk_x_g(potential_x as i32);
-                            let chunk_y_g = get_chunk_y_g(potential_y as i32);
-                            match chunk_map.map.get(&(chunk_x_g, chunk_y_g)) {
-                                Some(new_chunk) => {
-                                    chunk = new_chunk;
-                                },
-                                None => {
-                                }
-                            }
-                        }
-                    },
-                    None => {}
-                }
-                if !hoe_is_locked.bool {
-                    tool_position.translation.y = potential_y;
-                    tool_position.translation.x = potential_x;
-                } else {
-                    if tool_position.translation.x < potential_x {
-                        for y in (tool_position.translation.y as i32 - HOE_HEIGHT as i32/2..tool_position.translation.y as i32 + HOE_HEIGHT as i32/2).rev() {
-                            let _index = flatten_index(tool_position.translation.x as i32 + HOE_WIDTH as i32/2 + 1, y);
-                        } 
-                    }
-                }
-            }
+    let Some(position_c) = cursor_position.position else {
+        return
+    };
+    let angle = (position_c.y - player.0.translation.y).atan2(position_c.x - player.0.translation.x);
+    let mut potential_x = player.0.translation.x;
+    let mut potential_y = player.0.translation.y;
+    let dy = angle.sin();
+    let dx = angle.cos();
+    while matches!(chunk_map.get(potential_x as i32, potential_y as i32), Some(pixel) if registry.is_replaceable(pixel)) {
+        potential_x += dx as f32;
+        potential_y += dy as f32;
+        let distance_from_player_to_potential = distance(player.0.translation.x as i32, player.0.translation.y as i32, potential_x as i32, potential_y as i32);
+        if distance_from_player_to_potential > CURSOR_ORBITAL_RADIUS {
+            break
+        }
+        if distance(potential_x as i32, potential_y as i32, position_c.x as i32, position_c.y as i32) < 2. {
+            break
+        }
+    }
+    if !hoe_is_locked.bool {
+        tool_position.translation.y = potential_y;
+        tool_position.translation.x = potential_x;
+    } else {
+        if tool_position.translation.x < potential_x {
+            for y in (tool_position.translation.y as i32 - HOE_HEIGHT as i32/2..tool_position.translation.y as i32 + HOE_HEIGHT as i32/2).rev() {
+                let _index = flatten_index(tool_position.translation.x as i32 + HOE_WIDTH as i32/2 + 1, y);
+            } 
         }
     }
 }
 
-pub fn update_shovel_content_visual(shovel_image_grid: &mut Vec<u8>, shovel_contents: &Vec<u8>) {
+pub fn update_shovel_content_visual(shovel_image_grid: &mut Vec<u8>, shovel_contents: &Vec<u8>, registry: &MaterialRegistry) {
     for pixel in shovel_image_grid.iter_mut() {
-        if SHOVEL_ABLE.contains(pixel) {
+        if registry.get(*pixel).shovel.is_some() {
             *pixel = TRANSLUCENT_GREY;
         }
     }
@@ -235,107 +188,102 @@ pub fn update_shovel_content_visual(shovel_image_grid: &mut Vec<u8>, shovel_cont
     }
 }
 
-pub fn right_click_shovel(shovel_grid: &mut Vec<u8>, chunk_map: &mut HashMap<(i32, i32), Vec<u8>>, cursor_position: &Transform, cursor_contents: &mut Vec<u8>, gravity_coords: &mut GravityCoords) {
+pub fn update_shovel_visual(
+    shovel_query: Query<(&ContentList, &Handle<DefaultMaterial>), (With<ShovelTag>, Changed<ContentList>)>,
+    materials: Res<Assets<DefaultMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+    registry: Res<MaterialRegistry>,
+) {
+    let Ok((shovel_contents, shovel_material_handle)) = shovel_query.get_single() else {
+        return
+    };
+    let shovel_id = materials.get(shovel_material_handle).unwrap().color_map_handle.clone();
+    if let Some(shovel_image) = images.get_mut(&shovel_id) {
+        update_shovel_content_visual(&mut shovel_image.data, &shovel_contents.contents, &registry);
+    }
+}
+
+fn shovel_slots() -> Vec<(usize, usize)> {
+    let mut slots = Vec::new();
     for y in 0..CURSOR_RADIUS * 2 {
         for x in 0..CURSOR_RADIUS * 2 {
-            if cursor_contents.len() == 0 {
-                update_shovel_content_visual(shovel_grid, cursor_contents);
-                return
-            }
-            let shovel_grid_index = flatten_index_standard_grid(&x, &y, CURSOR_RADIUS * 2);
-            if SHOVEL_ABLE.contains(&shovel_grid[shovel_grid_index]) {
-                let (local_x, local_y) = (get_local_x(cursor_position.translation.x as i32 - CURSOR_RADIUS as i32 + x as i32), get_local_y(cursor_position.translation.y as i32 - CURSOR_RADIUS as i32 + (CURSOR_RADIUS * 2 - y - 1) as i32));
-                let local_index = flatten_index_standard_grid(&local_x, &local_y, CHUNK_SIZE as usize);
-                let (chunk_x_g, chunk_y_g) = (get_chunk_x_g(cursor_position.translation.x as i32 - CURSOR_RADIUS as i32 + x as i32), get_chunk_y_g(cursor_position.translation.y as i32 - CURSOR_RADIUS as i32 + (CURSOR_RADIUS * 2 - y - 1) as i32));
-                if chunk_map.get(&(chunk_x_g, chunk_y_g)).unwrap()[local_index] == SKY || chunk_map.get(&(chunk_x_g, chunk_y_g)).unwrap()[local_index] == LIGHT {
-                    let pixel = cursor_contents.pop().unwrap();
-                    chunk_map.get_mut(&(chunk_x_g, chunk_y_g)).unwrap()[local_index] = pixel;
-                    gravity_coords.coords.insert((cursor_position.translation.x as i32 - CURSOR_RADIUS as i32 + x as i32, cursor_position.translation.y as i32 - CURSOR_RADIUS as i32 + (CURSOR_RADIUS * 2 - y - 1) as i32));
-                }
+            if distance(x as i32, y as i32, CURSOR_RADIUS as i32, CURSOR_RADIUS as i32) < CURSOR_RADIUS as f32 - CURSOR_BORDER_WIDTH {
+                slots.push((x, y));
             }
         }
     }
-    update_shovel_content_visual(shovel_grid, cursor_contents);
+    slots
 }
 
-pub fn left_click_shovel(shovel_position: &Transform, shovel_contents: &mut Vec<u8>, chunk_map: &mut HashMap<(i32, i32), Vec<u8>>, shovel_grid: &mut Vec<u8>, gravity_coords: &mut GravityCoords, chunk_writer: &mut EventWriter<NewChunkEvent>) {
-    let left = shovel_position.translation.x as i32 - CURSOR_RADIUS as i32;
-    let right = shovel_position.translation.x as i32 + CURSOR_RADIUS as i32;
-    let top = shovel_position.translation.y as i32 + CURSOR_RADIUS as i32; 
-    let bottom = shovel_position.translation.y as i32 - CURSOR_RADIUS as i32;
-    let starting_count = shovel_contents.len();
-    for y in bottom..top {
-        for x in left..right {
-            if distance(x, y, shovel_position.translation.x as i32, shovel_position.translation.y as i32) < CURSOR_RADIUS as f32 - CURSOR_BORDER_WIDTH {
-                let (local_x, local_y) = (get_local_x(x), get_local_y(y));
-                let local_index = flatten_index_standard_grid(&local_x, &local_y, CHUNK_SIZE as usize);
-                let (chunk_x_g, chunk_y_g) = (get_chunk_x_g(x), get_chunk_y_g(y));
-                let comparing_pixel = chunk_map.get(&(chunk_x_g, chunk_y_g)).unwrap()[local_index];
-                if SHOVEL_ABLE.contains(&comparing_pixel) {
-                    shovel_contents.push(comparing_pixel);
-                    chunk_map.get_mut(&(chunk_x_g, chunk_y_g)).unwrap()[local_index] = SKY;
-                    if let Some(y) = search_upward_for_non_sky_pixel(chunk_map, x, y, chunk_writer) {
-                        gravity_coords.coords.insert((x, y));
-                    }
-                    if shovel_contents.len() == MAX_SHOVEL_CAPACITY {
-                        update_shovel_content_visual(shovel_grid, shovel_contents);
-                        return
-                    }
-                }
-            }
+pub fn right_click_shovel(chunk_map: &mut ChunkMap, cursor_position: &Transform, cursor_contents: &mut Vec<u8>, transaction: &mut EditTransaction, registry: &MaterialRegistry) {
+    let slots = shovel_slots();
+    let filled_slots = slots[slots.len() - cursor_contents.len().min(slots.len())..].to_vec();
+    for (x, y) in filled_slots {
+        if cursor_contents.len() == 0 {
+            return
+        }
+        let (x_g, y_g) = (cursor_position.translation.x as i32 - CURSOR_RADIUS as i32 + x as i32, cursor_position.translation.y as i32 - CURSOR_RADIUS as i32 + (CURSOR_RADIUS * 2 - y - 1) as i32);
+        // Pixels only land in empty space, then sink through lighter fluids once the simulation wakes them
+        if matches!(chunk_map.get(x_g, y_g), Some(pixel) if registry.is_replaceable(pixel)) {
+            let pixel = cursor_contents.pop().unwrap();
+            transaction.shovel_popped.push(pixel);
+            transaction.set(chunk_map, x_g, y_g, pixel).unwrap();
         }
-    }
-    if starting_count != shovel_contents.len() {
-        update_shovel_content_visual(shovel_grid, shovel_contents);
     }
 }
 
-pub fn left_click_pickaxe(pickaxe_position: &Transform, chunk_map: &mut HashMap<(i32, i32), Vec<u8>>, gravity_coords: &mut GravityCoords) {
-    let left = pickaxe_position.translation.x as i32 - CURSOR_RADIUS as i32;
-    let right = pickaxe_position.translation.x as i32 + CURSOR_RADIUS as i32;
-    let top = pickaxe_position.translation.y as i32 + CURSOR_RADIUS as i32; 
-    let bottom = pickaxe_position.translation.y as i32 - CURSOR_RADIUS as i32;
-    let mut gravel_variant_pmf = gravel_variant_pmf();
-    for y_g in bottom..top{
-        for x_g in left..right{
-            if distance(x_g, y_g, pickaxe_position.translation.x as i32, pickaxe_position.translation.y as i32) < CURSOR_RADIUS as f32 - CURSOR_BORDER_WIDTH {
-                let (local_x, local_y) = (get_local_x(x_g), get_local_y(y_g));
-                let local_index = flatten_index_standard_grid(&local_x, &local_y, CHUNK_SIZE as usize);
-                let (chunk_x_g, chunk_y_g) = (get_chunk_x_g(x_g), get_chunk_y_g(y_g));
-                if chunk_map.get(&(chunk_x_g, chunk_y_g)).unwrap()[local_index] == ROCK {
-                    chunk_map.get_mut(&(chunk_x_g, chunk_y_g)).unwrap()[local_index] = gravel_variant_pmf.next().unwrap();
-                    gravity_coords.coords.insert((x_g, y_g));
-                }
+pub fn left_click_shovel(shovel_position: &Transform, shovel_contents: &mut Vec<u8>, chunk_map: &mut ChunkMap, transaction: &mut EditTransaction, registry: &MaterialRegistry) {
+    let shovel_coords: Vec<(i32, i32)> = circle_coords(shovel_position.translation.x as i32, shovel_position.translation.y as i32, CURSOR_RADIUS as f32 - CURSOR_BORDER_WIDTH).collect();
+    for (x, y) in shovel_coords {
+        let Some(comparing_pixel) = chunk_map.get(x, y) else {
+            continue;
+        };
+        if let Some(collected) = registry.get(comparing_pixel).shovel {
+            shovel_contents.push(collected);
+            transaction.shovel_pushed.push(collected);
+            transaction.set(chunk_map, x, y, SKY).unwrap();
+            if let Some(y) = search_upward_for_non_sky_pixel(chunk_map, x, y, registry) {
+                chunk_map.wake(x, y);
+            }
+            if shovel_contents.len() == MAX_SHOVEL_CAPACITY {
+                return
             }
         }
     }
 }
 
-fn search_upward_for_non_sky_pixel(chunk_map: &mut HashMap<(i32, i32), Vec<u8>>, x_g: i32, y_g: i32, chunk_event_writer: &mut EventWriter<NewChunkEvent>) -> Option<i32> {
+pub fn left_click_pickaxe(pickaxe_position: &Transform, chunk_map: &mut ChunkMap, seed: u64, transaction: &mut EditTransaction, registry: &MaterialRegistry) {
+    let (center_x_g, center_y_g) = (pickaxe_position.translation.x as i32, pickaxe_position.translation.y as i32);
+    let radius = CURSOR_RADIUS as f32 - CURSOR_BORDER_WIDTH;
+    let pickaxe_coords: Vec<(i32, i32)> = circle_coords(center_x_g, center_y_g, radius).collect();
+    for (x_g, y_g) in pickaxe_coords {
+        let Some(pixel) = chunk_map.get(x_g, y_g) else {
+            continue;
+        };
+        let Some(broken) = registry.pickaxe_result(pixel, seed, x_g, y_g) else {
+            continue;
+        };
+        let hit = PICKAXE_POWER * (1. - PICKAXE_FALLOFF * distance(x_g, y_g, center_x_g, center_y_g) / radius);
+        if chunk_map.add_damage(x_g, y_g, hit / registry.get(pixel).hardness) >= 1. {
+            transaction.set(chunk_map, x_g, y_g, broken).unwrap();
+        }
+    }
+}
+
+fn search_upward_for_non_sky_pixel(chunk_map: &ChunkMap, x_g: i32, y_g: i32, registry: &MaterialRegistry) -> Option<i32> {
     let mut y_level = 1;
     while y_g + y_level < y_g + CURSOR_ORBITAL_RADIUS as i32 * 2 {
-        let local_x = get_local_x(x_g);
-        let local_y = get_local_y(y_g + y_level);
-        let local_index = flatten_index_standard_grid(&local_x, &local_y, CHUNK_SIZE as usize);
-        let (chunk_x_g, chunk_y_g) = (x_g / CHUNK_SIZE as i32, (y_g + y_level) / CHUNK_SIZE as i32);
-        if let Some(chunk) = chunk_map.get(&(chunk_x_g, chunk_y_g)) {
-            if chunk[local_index] != SKY {
+        if let Some(pixel) = chunk_map.get(x_g, y_g + y_level) {
+            if !registry.is_replaceable(pixel) {
                 return Some(y_g + y_level)
             }
-        } else {
-            match chunk_map.get_mut(&(chunk_x_g, chunk_y_g)) {
-                Some(_) => {},
-                None => {
-                    chunk_event_writer.send(NewChunkEvent { chunk_x_g, chunk_y_g });
-                }
-            }
         }
         y_level += 1;
     }
     None
 }
 
-pub fn left_click_hoe(_hoe_position_c: &mut Transform, _grid: &mut HashMap<(i32, i32), Vec<u8>>, is_locked: &mut bool) {
+pub fn left_click_hoe(_hoe_position_c: &mut Transform, _chunk_map: &mut ChunkMap, is_locked: &mut bool) {
     // for x in (hoe_position_c.translation.x - HOE_WIDTH as f32 /2.) as i32 .. (hoe_position_c.translation.x + HOE_WIDTH as f32 / 2.) as i32 {
     //     for y in (hoe_position_c.translation.y - HOE_HEIGHT as f32 / 2.) as i32 .. (hoe_position_c.translation.y + HOE_HEIGHT as f32 / 2.) as i32{
     //         let index = flatten_index(x as i32, y as i32);
@@ -359,4 +307,47 @@ pub fn left_click_hoe(_hoe_position_c: &mut Transform, _grid: &mut HashMap<(i32,
 
 pub fn right_click_hoe(is_locked: &mut bool) {
     *is_locked = false;
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use bevy::prelude::Transform;
+
+    use crate::{chunk::{Chunk, DirtyRect}, color_map::{GRAVEL1, ROCK, SKY, WATER}, components::ChunkMap, history::EditTransaction, material_registry::MaterialRegistry, tools::{left_click_pickaxe, right_click_shovel}};
+
+    #[test]
+    fn test_pickaxe_needs_several_hits_on_rock() {
+        let mut map = HashMap::new();
+        map.insert((0, 0), Chunk::Uniform(ROCK));
+        let mut chunk_map = ChunkMap::new(map);
+        let registry = MaterialRegistry::default();
+        let position = Transform::from_xyz(0., 0., 0.);
+        left_click_pickaxe(&position, &mut chunk_map, 1, &mut EditTransaction::default(), &registry);
+        assert_eq!(chunk_map.get(0, 0), Some(ROCK));
+        assert!(chunk_map.damage(0, 0) > 0. && chunk_map.damage(0, 0) < 1.);
+        assert!(chunk_map.damage(0, 0) > chunk_map.damage(10, 0));
+        let damage_image = chunk_map.damage_rect_data((0, 0), &DirtyRect::full());
+        assert_eq!(damage_image.iter().filter(|damage| **damage > 0).count(), chunk_map.damage[&(0, 0)].len());
+        left_click_pickaxe(&position, &mut chunk_map, 1, &mut EditTransaction::default(), &registry);
+        assert_ne!(chunk_map.get(0, 0), Some(ROCK));
+        assert_eq!(chunk_map.damage(0, 0), 0.);
+        assert_eq!(chunk_map.get(10, 0), Some(ROCK));
+    }
+
+    #[test]
+    fn test_shovel_only_drops_into_empty_space() {
+        let mut map = HashMap::new();
+        map.insert((0, 0), Chunk::Uniform(SKY));
+        let mut chunk_map = ChunkMap::new(map);
+        chunk_map.fill_rect(-50, -50, 50, 0, WATER).unwrap();
+        let water = |chunk_map: &ChunkMap| chunk_map.pixels_in_rect(-50, -50, 50, 50).filter(|(_, _, pixel)| *pixel == Some(WATER)).map(|(x_g, y_g, _)| (x_g, y_g)).collect::<Vec<_>>();
+        let water_before = water(&chunk_map);
+        let mut shovel_contents = vec![GRAVEL1; 1000];
+        right_click_shovel(&mut chunk_map, &Transform::from_xyz(0., 0., 0.), &mut shovel_contents, &mut EditTransaction::default(), &MaterialRegistry::default());
+        let gravel = chunk_map.pixels_in_rect(-50, -50, 50, 50).filter(|(_, _, pixel)| *pixel == Some(GRAVEL1)).count();
+        assert_eq!(water(&chunk_map), water_before);
+        assert!(gravel > 0);
+        assert_eq!(gravel + shovel_contents.len(), 1000);
+    }
+}
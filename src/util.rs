@@ -1,12 +1,13 @@
+use std::collections::HashSet;
 use std::io::{self, Write};
 use std::fs::File;
 
 use bevy::{math::Vec3, prelude::Image, render::{render_asset::RenderAssetUsages, render_resource::{Extent3d, TextureDimension, TextureFormat}}};
 
-use crate::constants::CHUNK_SIZE;
+use crate::constants::chunk_size;
 
 pub fn flatten_index(x: i32, y: i32) -> usize {
-    let index = ((CHUNK_SIZE as i32 / 2) - y) * CHUNK_SIZE as i32 + (x + CHUNK_SIZE as i32 / 2);
+    let index = ((chunk_size() as i32 / 2) - y) * chunk_size() as i32 + (x + chunk_size() as i32 / 2);
     return index as usize;
 }
 
@@ -23,13 +24,13 @@ pub fn grid_to_image(grid: &Vec<u8>, width: u32, height: u32, _perlin_mask: Opti
 }
 
 pub fn c_to_tl(entity_position_c: &Vec3, width: f32, height: f32) -> (f32, f32) {
-    (entity_position_c.x + CHUNK_SIZE/2. - width/2., entity_position_c.y - CHUNK_SIZE/2. * -1. - height/2.)
+    (entity_position_c.x + chunk_size()/2. - width/2., entity_position_c.y - chunk_size()/2. * -1. - height/2.)
 }
 
 pub fn tl_to_c(x: f32, y: f32, width: f32, height: f32) -> Vec3 {
     Vec3 {
-        x: x + width/2. - CHUNK_SIZE as f32/2.,
-        y: (y + height/2.) * -1. + CHUNK_SIZE/2.,
+        x: x + width/2. - chunk_size() as f32/2.,
+        y: (y + height/2.) * -1. + chunk_size()/2.,
         z: 0.
     }
 }
@@ -44,6 +45,15 @@ pub fn flatten_index_standard_grid(x: &usize, y: &usize, grid_width: usize) -> u
     y * grid_width + x
 }
 
+pub fn arg_value(name: &str) -> Option<String> {
+    let args: Vec<String> = std::env::args().collect();
+    args.iter().position(|arg| arg == name).and_then(|index| args.get(index + 1)).cloned()
+}
+
+pub fn has_arg(name: &str) -> bool {
+    std::env::args().any(|arg| arg == name)
+}
+
 pub fn distance(x1: i32, y1: i32, x2: i32, y2: i32) -> f32 {
     ((x1 as f32 - x2 as f32).powi(2) + (y1 as f32 - y2 as f32).powi(2)).sqrt()
 }
@@ -125,46 +135,56 @@ pub fn valid_machine_spawn(_chunk_map: &Vec<Vec<u8>>, _position_g: Vec3, _width:
 
 //global_chunk_index and top left Y to world coordinate:
 pub fn get_global_y_coordinate(chunk_y_g: i32, local_y: usize) -> i32 {
-    chunk_y_g * CHUNK_SIZE as i32 + CHUNK_SIZE as i32 / 2 as i32 - local_y as i32
+    chunk_y_g * chunk_size() as i32 + chunk_size() as i32 / 2 as i32 - local_y as i32
 }
 
 pub fn get_global_x_coordinate(chunk_x_g: i32, local_x: usize) -> i32 {
-    chunk_x_g * CHUNK_SIZE as i32 - CHUNK_SIZE as i32 / 2 as i32 + local_x as i32
+    chunk_x_g * chunk_size() as i32 - chunk_size() as i32 / 2 as i32 + local_x as i32
 }
 
 pub fn get_local_x(global_x: i32) -> usize {
-    let x = CHUNK_SIZE as i32 / 2 + (global_x % CHUNK_SIZE as i32);
-    if x >= CHUNK_SIZE as i32{
-        return (x - CHUNK_SIZE as i32) as usize;
+    let x = chunk_size() as i32 / 2 + (global_x % chunk_size() as i32);
+    if x >= chunk_size() as i32{
+        return (x - chunk_size() as i32) as usize;
     } else if x < 0 {
-        return (CHUNK_SIZE as i32 + x) as usize;
+        return (chunk_size() as i32 + x) as usize;
     }
     x as usize
 }
 
 pub fn get_local_y(global_x: i32) -> usize {
-    (CHUNK_SIZE as i32/2 - 1 - global_x).rem_euclid(CHUNK_SIZE as i32) as usize
+    (chunk_size() as i32/2 - 1 - global_x).rem_euclid(chunk_size() as i32) as usize
 }
 
 pub fn get_chunk_x_g(x_g: i32) -> i32 {
-    (x_g + CHUNK_SIZE as i32 / 2).div_euclid(CHUNK_SIZE as i32)
+    (x_g + chunk_size() as i32 / 2).div_euclid(chunk_size() as i32)
 }
 
 pub fn get_chunk_y_g(y_g: i32) -> i32 {
-    (y_g + CHUNK_SIZE as i32 / 2).div_euclid(CHUNK_SIZE as i32)
+    (y_g + chunk_size() as i32 / 2).div_euclid(chunk_size() as i32)
+}
+
+pub fn chunks_in_area(center_x_g: i32, center_y_g: i32, half_width: i32, half_height: i32) -> HashSet<(i32, i32)> {
+    let mut chunks = HashSet::new();
+    for y_g in [center_y_g - half_height, center_y_g + half_height] {
+        for x_g in [center_x_g - half_width, center_x_g + half_width] {
+            chunks.insert((get_chunk_x_g(x_g), get_chunk_y_g(y_g)));
+        }
+    }
+    chunks
 }
 
 pub fn local_to_global_x(chunk_x_g: i32, local_x: usize) -> i32 {
-    chunk_x_g * CHUNK_SIZE as i32 + local_x as i32 - CHUNK_SIZE as i32 / 2
+    chunk_x_g * chunk_size() as i32 + local_x as i32 - chunk_size() as i32 / 2
 }
 
 pub fn local_to_global_y(chunk_y_g: i32, local_y: usize) -> i32 {
-    chunk_y_g * CHUNK_SIZE as i32 + CHUNK_SIZE as i32 / 2 - local_y as i32
+    chunk_y_g * chunk_size() as i32 + chunk_size() as i32 / 2 - local_y as i32
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{constants::CHUNK_SIZE, util::{get_chunk_x_g, get_chunk_y_g, get_local_x, get_local_y, local_to_global_x}};
+    use crate::{constants::chunk_size, util::{get_chunk_x_g, get_chunk_y_g, get_local_x, get_local_y, local_to_global_x}};
 
     #[test]
     fn test_get_local_y() {
@@ -196,15 +216,15 @@ mod tests {
     fn test_get_local_x() {
         assert_eq!(get_local_x(-301), 599);
         assert_eq!(get_local_x(-400), 500);
-        assert_eq!(get_local_x(0), CHUNK_SIZE as usize / 2);
+        assert_eq!(get_local_x(0), chunk_size() as usize / 2);
         assert_eq!(get_local_x(-300), 0);
-        assert_eq!(get_local_x(300 + 1 * CHUNK_SIZE as i32 + 200), 200);
+        assert_eq!(get_local_x(300 + 1 * chunk_size() as i32 + 200), 200);
         assert_eq!(get_local_x(301), 1);
         assert_eq!(get_local_x(400), 100);
         assert_eq!(get_local_x(0), 300);
         assert_eq!(get_local_x(100), 400);
         assert_eq!(get_local_x(-100), 200);
-        assert_eq!(get_local_x(300 + -1 * CHUNK_SIZE as i32 + 200), 200);
+        assert_eq!(get_local_x(300 + -1 * chunk_size() as i32 + 200), 200);
         assert_eq!(get_local_x(300), 0);
     }
 
@@ -216,11 +236,11 @@ mod tests {
 
     #[test]
     fn test_local_to_global_x() {
-        assert_eq!(local_to_global_x(0, (CHUNK_SIZE as i32/2) as usize), 0);
+        assert_eq!(local_to_global_x(0, (chunk_size() as i32/2) as usize), 0);
     }
 
     #[test]
     fn test_local_to_global_y() {
-        assert_eq!(super::local_to_global_y(0, (CHUNK_SIZE as i32/2) as usize), 0);
+        assert_eq!(super::local_to_global_y(0, (chunk_size() as i32/2) as usize), 0);
     }
 }
\ No newline at end of file
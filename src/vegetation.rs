@@ -0,0 +1,142 @@
+use noise::Perlin;
+use rand::Rng;
+
+use crate::biome::column_profile;
+use crate::color_map::{LEAVES, SKY, TALL_GRASS, TRUNK};
+use crate::constants::{chunk_size, BUSH_RADIUS, LEAF_FILL, TALL_GRASS_MAX_HEIGHT, TREE_CANOPY_RADIUS, TREE_MAX_HEIGHT, TREE_MIN_HEIGHT, TREE_SPACING};
+use crate::material_registry::material_registry;
+use crate::seed::{chunk_rng, coordinate_hash, pixel_rng, PLANT_STREAM, TREE_STREAM};
+use crate::terrain_config::terrain_config;
+use crate::util::{get_global_x_coordinate, get_global_y_coordinate};
+use crate::water::Lake;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PlantKind {
+    Tree { height: i32, radius: i32 },
+    Bush { radius: i32 },
+    TallGrass { height: i32 },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Plant {
+    pub kind: PlantKind,
+    pub x_g: i32,
+    pub base_y_g: i32,
+}
+
+pub fn plant_at(perlin: &Perlin, seed: u64, x_g: i32) -> Option<Plant> {
+    let profile = column_profile(terrain_config(), perlin, seed, x_g);
+    if !material_registry().get(profile.material_at(profile.surface, 0)).fertile {
+        return None;
+    }
+    let vegetation = profile.biome.vegetation;
+    let base_y_g = profile.surface + 1;
+    let cell = x_g.div_euclid(TREE_SPACING);
+    let mut tree_rng = chunk_rng(seed, cell, 0, TREE_STREAM);
+    let (tree_roll, offset) = (tree_rng.random::<f32>(), tree_rng.random_range(0..TREE_SPACING - 1));
+    if tree_roll < vegetation.trees && x_g == cell * TREE_SPACING + offset {
+        let kind = PlantKind::Tree { height: tree_rng.random_range(TREE_MIN_HEIGHT..=TREE_MAX_HEIGHT), radius: tree_rng.random_range(3..=TREE_CANOPY_RADIUS) };
+        return Some(Plant { kind, x_g, base_y_g });
+    }
+    let mut plant_rng = pixel_rng(seed, x_g, 0, PLANT_STREAM);
+    let roll = plant_rng.random::<f32>();
+    let kind = if roll < vegetation.bushes {
+        PlantKind::Bush { radius: plant_rng.random_range(1..=BUSH_RADIUS) }
+    } else if roll < vegetation.bushes + vegetation.tall_grass {
+        PlantKind::TallGrass { height: plant_rng.random_range(1..=TALL_GRASS_MAX_HEIGHT) }
+    } else {
+        return None;
+    };
+    Some(Plant { kind, x_g, base_y_g })
+}
+
+fn leaf_grows(seed: u64, x_g: i32, y_g: i32) -> bool {
+    (coordinate_hash(seed, x_g, y_g, PLANT_STREAM) as f64 / u64::MAX as f64) < LEAF_FILL
+}
+
+fn plant_pixels(plant: &Plant, seed: u64) -> Vec<(i32, i32, u8)> {
+    let mut pixels = Vec::new();
+    match plant.kind {
+        PlantKind::Tree { height, radius } => {
+            let top_y_g = plant.base_y_g + height;
+            for y_g in plant.base_y_g - 3..top_y_g {
+                pixels.push((plant.x_g, y_g, TRUNK));
+                pixels.push((plant.x_g + 1, y_g, TRUNK));
+            }
+            for dy in -radius..=radius {
+                for dx in -radius..=radius + 1 {
+                    let (x_g, y_g) = (plant.x_g + dx, top_y_g + dy);
+                    if (dx as f32 - 0.5).powi(2) + (dy * dy) as f32 <= (radius as f32 + 0.5).powi(2) && leaf_grows(seed, x_g, y_g) {
+                        pixels.push((x_g, y_g, LEAVES));
+                    }
+                }
+            }
+        },
+        PlantKind::Bush { radius } => {
+            for dy in 0..=radius {
+                for dx in -radius..=radius {
+                    let (x_g, y_g) = (plant.x_g + dx, plant.base_y_g + dy);
+                    if dx * dx + dy * dy <= radius * radius && leaf_grows(seed, x_g, y_g) {
+                        pixels.push((x_g, y_g, LEAVES));
+                    }
+                }
+            }
+        },
+        PlantKind::TallGrass { height } => {
+            for y_g in plant.base_y_g..plant.base_y_g + height {
+                pixels.push((plant.x_g, y_g, TALL_GRASS));
+            }
+        },
+    }
+    pixels
+}
+
+pub fn place_vegetation(grid: &mut [u8], chunk_x_g: i32, chunk_y_g: i32, lakes: &[Lake], perlin: &Perlin, seed: u64) {
+    let reach = TREE_CANOPY_RADIUS.max(BUSH_RADIUS) + 1;
+    let left_x_g = get_global_x_coordinate(chunk_x_g, 0);
+    let top_y_g = get_global_y_coordinate(chunk_y_g, 0);
+    for source_x_g in left_x_g - reach..left_x_g + chunk_size() as i32 + reach {
+        let Some(plant) = plant_at(perlin, seed, source_x_g) else {
+            continue;
+        };
+        if lakes.iter().any(|lake| lake.contains_column(plant.x_g) || lake.contains_column(plant.x_g + 1)) {
+            continue;
+        }
+        for (x_g, y_g, material) in plant_pixels(&plant, seed) {
+            let (x, y) = (x_g - left_x_g, top_y_g - y_g);
+            if (0..chunk_size() as i32).contains(&x) && (0..chunk_size() as i32).contains(&y) {
+                let index = y as usize * chunk_size() as usize + x as usize;
+                if grid[index] == SKY || (material == TRUNK && matches!(grid[index], LEAVES | TALL_GRASS)) {
+                    grid[index] = material;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use noise::Perlin;
+
+    use crate::{chunk_generator::generate_chunk, color_map::TRUNK, constants::chunk_size, util::{get_global_x_coordinate, get_global_y_coordinate}, vegetation::{plant_at, PlantKind}};
+
+    #[test]
+    fn test_trees_grow_on_grass_surface() {
+        let perlin = Perlin::new(9);
+        let seed = 9;
+        let tree = (0..20000).find_map(|x_g| plant_at(&perlin, seed, x_g).filter(|plant| matches!(plant.kind, PlantKind::Tree { .. }))).unwrap();
+        let PlantKind::Tree { height, .. } = tree.kind else {
+            unreachable!();
+        };
+        let chunk_x_g = (-1000..1000).find(|chunk_x_g| (0..chunk_size() as i32).contains(&(tree.x_g - get_global_x_coordinate(*chunk_x_g, 0)))).unwrap();
+        let mut grids = HashMap::new();
+        for y_g in tree.base_y_g..tree.base_y_g + height {
+            let chunk_y_g = (-8..8).find(|chunk_y_g| (0..chunk_size() as i32).contains(&(get_global_y_coordinate(*chunk_y_g, 0) - y_g))).unwrap();
+            let grid = grids.entry(chunk_y_g).or_insert_with(|| generate_chunk(chunk_x_g, chunk_y_g, &perlin, seed));
+            let index = (get_global_y_coordinate(chunk_y_g, 0) - y_g) * chunk_size() as i32 + tree.x_g - get_global_x_coordinate(chunk_x_g, 0);
+            assert_eq!(grid[index as usize], TRUNK);
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use noise::Perlin;
+use rand::Rng;
+
+use crate::biome::{column_profile, surfaces_under};
+use crate::color_map::{SKY, WATER};
+use crate::constants::{chunk_size, LAKE_MAX_DEPTH, LAKE_MAX_SURFACE_RANGE, LAKE_MAX_WIDTH, LAKE_MIN_DEPTH, LAKE_MIN_WIDTH, WATER_POCKET_CHANCE, WATER_POCKET_MAX_RADIUS, WATER_POCKET_MIN_DEPTH, WATER_POCKET_MIN_RADIUS};
+use crate::seed::{chunk_rng, LAKE_STREAM, WATER_POCKET_STREAM};
+use crate::terrain_config::terrain_config;
+use crate::util::{get_global_x_coordinate, get_global_y_coordinate};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Lake {
+    pub left_x_g: i32,
+    pub width: i32,
+    pub level: i32,
+    pub depth: i32,
+}
+
+impl Lake {
+    pub fn contains_column(&self, x_g: i32) -> bool {
+        (self.left_x_g..self.left_x_g + self.width).contains(&x_g)
+    }
+
+    pub fn bottom(&self, x_g: i32) -> i32 {
+        let half = (self.width - 1) as f32 / 2.;
+        let t = (x_g - self.left_x_g) as f32 / half - 1.;
+        self.level - (self.depth as f32 * (1. - t * t).max(0.).sqrt()).round() as i32
+    }
+}
+
+pub fn lake_at(seed: u64, chunk_x_g: i32, perlin: &Perlin) -> Option<Lake> {
+    let mut rng = chunk_rng(seed, chunk_x_g, 0, LAKE_STREAM);
+    let (roll, x) = (rng.random::<f32>(), rng.random_range(0..chunk_size() as usize));
+    let (width, depth) = (rng.random_range(LAKE_MIN_WIDTH..=LAKE_MAX_WIDTH), rng.random_range(LAKE_MIN_DEPTH..=LAKE_MAX_DEPTH));
+    let left_x_g = get_global_x_coordinate(chunk_x_g, x);
+    if roll >= column_profile(terrain_config(), perlin, seed, left_x_g + width / 2).biome.lakes {
+        return None;
+    }
+    let (low, high) = surfaces_under(perlin, seed, left_x_g, width as usize);
+    if high - low > LAKE_MAX_SURFACE_RANGE {
+        return None;
+    }
+    let rim = |x_g: i32| column_profile(terrain_config(), perlin, seed, x_g).surface;
+    let level = rim(left_x_g).min(rim(left_x_g + width - 1));
+    Some(Lake { left_x_g, width, level, depth })
+}
+
+pub fn lakes_near(seed: u64, chunk_x_g: i32, perlin: &Perlin) -> Vec<Lake> {
+    let reach = (LAKE_MAX_WIDTH as f32 / chunk_size()).ceil() as i32;
+    (chunk_x_g - reach..=chunk_x_g).filter_map(|source_x_g| lake_at(seed, source_x_g, perlin)).collect()
+}
+
+pub fn place_lakes(grid: &mut [u8], chunk_x_g: i32, chunk_y_g: i32, lakes: &[Lake], perlin: &Perlin, seed: u64) {
+    let size = chunk_size() as i32;
+    let left_x_g = get_global_x_coordinate(chunk_x_g, 0);
+    let top_y_g = get_global_y_coordinate(chunk_y_g, 0);
+    for lake in lakes {
+        for x_g in lake.left_x_g.max(left_x_g)..(lake.left_x_g + lake.width).min(left_x_g + size) {
+            let surface = column_profile(terrain_config(), perlin, seed, x_g).surface;
+            let bottom = lake.bottom(x_g);
+            for y_g in (bottom + 1).max(top_y_g - size + 1)..=surface.max(lake.level).min(top_y_g) {
+                let index = ((top_y_g - y_g) * size + x_g - left_x_g) as usize;
+                grid[index] = if y_g <= lake.level { WATER } else { SKY };
+            }
+        }
+    }
+}
+
+pub fn place_water_pockets(grid: &mut [u8], size: i32, chunk_x_g: i32, chunk_y_g: i32, perlin: &Perlin, seed: u64) {
+    let mut rng = chunk_rng(seed, chunk_x_g, chunk_y_g, WATER_POCKET_STREAM);
+    if rng.random::<f32>() >= WATER_POCKET_CHANCE {
+        return;
+    }
+    let radius = rng.random_range(WATER_POCKET_MIN_RADIUS..=WATER_POCKET_MAX_RADIUS).min(size / 2 - 1);
+    let (x, y) = (rng.random_range(radius..size - radius), rng.random_range(radius..size - radius));
+    let center_x_g = get_global_x_coordinate(chunk_x_g, x as usize);
+    let center_y_g = get_global_y_coordinate(chunk_y_g, y as usize);
+    let profile = column_profile(terrain_config(), perlin, seed, center_x_g);
+    if center_y_g + radius > profile.rock_line || profile.surface - center_y_g < WATER_POCKET_MIN_DEPTH {
+        return;
+    }
+    for dy in -radius..=radius {
+        for dx in -radius..=radius {
+            if dx * dx + dy * dy <= radius * radius {
+                grid[((y + dy) * size + x + dx) as usize] = WATER;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use noise::Perlin;
+
+    use crate::{chunk_generator::generate_chunk, color_map::{SKY, WATER}, constants::chunk_size, util::{get_global_x_coordinate, get_global_y_coordinate}, water::{lake_at, place_water_pockets}};
+
+    #[test]
+    fn test_lakes_fill_flat_to_their_level() {
+        let perlin = Perlin::new(5);
+        let seed = 5;
+        let (chunk_x_g, lake) = (-400..400).find_map(|chunk_x_g| lake_at(seed, chunk_x_g, &perlin).map(|lake| (chunk_x_g, lake))).unwrap();
+        let size = chunk_size() as i32;
+        let chunk_y_g = (-8..8).find(|chunk_y_g| (1..size).contains(&(get_global_y_coordinate(*chunk_y_g, 0) - lake.level))).unwrap();
+        let grids: Vec<Vec<u8>> = (chunk_x_g..=chunk_x_g + 1).map(|source_x_g| generate_chunk(source_x_g, chunk_y_g, &perlin, seed)).collect();
+        let pixel = |x_g: i32, y_g: i32| {
+            let source = (x_g - get_global_x_coordinate(chunk_x_g, 0)) / size;
+            let x = x_g - get_global_x_coordinate(chunk_x_g + source, 0);
+            grids[source as usize][((get_global_y_coordinate(chunk_y_g, 0) - y_g) * size + x) as usize]
+        };
+        let middle = lake.left_x_g + lake.width / 2;
+        assert_eq!(pixel(middle, lake.level), WATER);
+        assert_eq!(pixel(middle, lake.level + 1), SKY);
+        for x_g in lake.left_x_g + 1..lake.left_x_g + lake.width - 1 {
+            assert_ne!(pixel(x_g, lake.level + 1), WATER);
+        }
+    }
+
+    #[test]
+    fn test_water_pockets_fit_in_small_chunks() {
+        let perlin = Perlin::new(3);
+        let mut pockets = 0;
+        for chunk_y_g in -300..50 {
+            for chunk_x_g in -4..4 {
+                let mut grid = vec![SKY; 16];
+                place_water_pockets(&mut grid, 4, chunk_x_g, chunk_y_g, &perlin, 3);
+                pockets += grid.contains(&WATER) as usize;
+            }
+        }
+        assert!(pockets > 0);
+    }
+}